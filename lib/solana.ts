@@ -40,9 +40,19 @@ export const getOfferPDA = (seller: PublicKey, offerId: bigint) => {
 /**
  * Derives the investor vault PDA
  */
-export function getInvestorVaultPDA(investor: PublicKey): [PublicKey, number] {
+export function getInvestorVaultPDA(investor: PublicKey, mint: PublicKey = USDC_MINT): [PublicKey, number] {
+    // USDC vaults predate the quote mint registry and are not keyed by mint
+    const seeds = [new TextEncoder().encode("vault"), investor.toBuffer()];
+    if (!mint.equals(USDC_MINT)) seeds.push(mint.toBuffer());
+    return PublicKey.findProgramAddressSync(seeds, PROGRAM_ID);
+}
+
+/**
+ * Derives the quote mint registry PDA
+ */
+export function getQuoteMintPDA(mint: PublicKey): [PublicKey, number] {
     return PublicKey.findProgramAddressSync(
-        [new TextEncoder().encode("vault"), investor.toBuffer()],
+        [new TextEncoder().encode("quote_mint"), mint.toBuffer()],
         PROGRAM_ID
     );
 }
@@ -69,6 +79,28 @@ export function getCompanyPDA(companyId: number): [PublicKey, number] {
     );
 }
 
+/**
+ * Derives the platform config PDA
+ */
+export function getPlatformConfigPDA(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [new TextEncoder().encode("platform_config")],
+        PROGRAM_ID
+    );
+}
+
+/**
+ * Derives an investor's legal agreement acceptance PDA for a company
+ */
+export function getAgreementPDA(companyId: number, investor: PublicKey): [PublicKey, number] {
+    const companyIdBuffer = new Uint8Array(8);
+    new DataView(companyIdBuffer.buffer).setBigUint64(0, BigInt(companyId), true);
+    return PublicKey.findProgramAddressSync(
+        [new TextEncoder().encode("agreement"), companyIdBuffer, investor.toBuffer()],
+        PROGRAM_ID
+    );
+}
+
 /**
  * Derives the position account PDA
  */
@@ -169,17 +201,23 @@ export function buildDepositTx(
     vault: PublicKey,
     investorTokenAccount: PublicKey,
     vaultTokenAccount: PublicKey,
-    amount: bigint
+    amount: bigint,
+    mint: PublicKey = USDC_MINT,
+    tokenProgram: PublicKey = TOKEN_PROGRAM_ID
 ) {
+    const [quoteMint] = getQuoteMintPDA(mint);
+
     return (program as any).methods
         .deposit(new BN(amount.toString()))
         .accounts({
             investor,
             investorVault: vault,
+            quoteMint,
+            mint,
             investorTokenAccount,
             vaultTokenAccount,
             incoLightningProgram: INCO_LIGHTNING_ID,
-            tokenProgram: TOKEN_PROGRAM_ID,
+            tokenProgram,
             systemProgram: SystemProgram.programId,
         });
 }
@@ -208,43 +246,59 @@ export function buildAuthorizeDecryptionTx(
         });
 }
 
+/**
+ * Builds a primary purchase of common stock. `quoteMint` is the company's quote currency,
+ * which selects the vault paid from.
+ */
 export function buildBuySharesTx(
     program: Program<any>,
     investor: PublicKey,
     companyId: number,
     companyAccount: PublicKey,
     eSharesHandle: BN, // Encrypted handle from Inco SDK
-    remainingAccounts: { pubkey: PublicKey; isWritable: boolean; isSigner: boolean; }[]
+    remainingAccounts: { pubkey: PublicKey; isWritable: boolean; isSigner: boolean; }[],
+    quoteMint: PublicKey = USDC_MINT
 ) {
-    const [vault] = getInvestorVaultPDA(investor);
+    const [platformConfig] = getPlatformConfigPDA();
+    const [vault] = getInvestorVaultPDA(investor, quoteMint);
     const [position] = getPositionPDA(companyId, investor);
+    const [agreementAcceptance] = getAgreementPDA(companyId, investor);
 
-    return (program.methods as any).buyShares({ u128: eSharesHandle })
+    return (program.methods as any).buyShares({ u128: eSharesHandle }, 0)
         .accounts({
             investor,
+            platformConfig,
             investorVault: vault,
             companyAccount,
+            shareClass: null, // Common stock
             position,
+            agreementAcceptance,
             incoLightningProgram: INCO_LIGHTNING_ID,
             systemProgram: SystemProgram.programId,
         })
         .remainingAccounts(remainingAccounts);
 }
 
+/**
+ * Builds a sale of common stock back to the company, paid into the `quoteMint` vault.
+ */
 export function buildSellSharesTx(
     program: Program<any>,
     investor: PublicKey,
     companyId: number,
     companyAccount: PublicKey,
     eSharesHandle: BN, // Encrypted handle from Inco SDK
-    remainingAccounts: { pubkey: PublicKey; isWritable: boolean; isSigner: boolean; }[]
+    remainingAccounts: { pubkey: PublicKey; isWritable: boolean; isSigner: boolean; }[],
+    quoteMint: PublicKey = USDC_MINT
 ) {
-    const [vault] = getInvestorVaultPDA(investor);
+    const [platformConfig] = getPlatformConfigPDA();
+    const [vault] = getInvestorVaultPDA(investor, quoteMint);
     const [position] = getPositionPDA(companyId, investor);
 
     return (program.methods as any).sellShares({ u128: eSharesHandle })
         .accounts({
             investor,
+            platformConfig,
             investorVault: vault,
             companyAccount,
             position,
@@ -266,6 +320,7 @@ export function buildActivateCompanyTx(
     initialShares: bigint,
     pricePerShare: bigint
 ) {
+    const [platformConfig] = getPlatformConfigPDA();
     const [companyAccount] = getCompanyPDA(companyId);
 
     const method = (program.methods as any).activateCompany || (program.methods as any).activate_company;
@@ -278,6 +333,7 @@ export function buildActivateCompanyTx(
         new BN(pricePerShare.toString()),
     )
         .accounts({
+            platform_config: platformConfig,
             platform_admin: platformAdmin,
             company_account: companyAccount,
             system_program: SystemProgram.programId,
@@ -294,14 +350,18 @@ export function buildUpdateOfferingTx(
     companyId: number,
     newPrice: bigint,
     addShares: bigint,
-    active: boolean
+    active: boolean,
+    rightsPeriodSeconds: number = 0,
+    outstandingShares: bigint = BigInt(0)
 ) {
     const [companyAccount] = getCompanyPDA(companyId);
 
     return (program.methods as any).updateOffering(
         new BN(newPrice.toString()),
         new BN(addShares.toString()),
-        active
+        active,
+        new BN(rightsPeriodSeconds),
+        new BN(outstandingShares.toString())
     )
         .accounts({
             company_admin: companyAdmin,
@@ -359,6 +419,17 @@ export async function fetchInvestorVault(program: Program<any>, investor: Public
     }
 }
 
+// PositionAccount layout (v3): discriminator(8) + version(1) + owner(32) + company_id(8)
+// + encrypted_shares(16) + bump(1) + class_id(1) + reserved(31)
+const POSITION_OWNER_OFFSET = 9;
+const POSITION_COMPANY_ID_OFFSET = 41;
+const POSITION_SHARES_OFFSET = 49;
+const POSITION_BUMP_OFFSET = 65;
+const POSITION_CLASS_ID_OFFSET = 66;
+
+// CompanyAccount layout (v3): discriminator(8) + version(1) + company_id(8) + company_admin(32) + ...
+const COMPANY_ADMIN_OFFSET = 17;
+
 /**
  * Fetches all positions for an investor (real on-chain data)
  */
@@ -376,7 +447,7 @@ export async function fetchInvestorPositions(connection: Connection, investor: P
                 },
                 {
                     memcmp: {
-                        offset: 8, // Owner (32 bytes)
+                        offset: POSITION_OWNER_OFFSET,
                         bytes: investor.toBase58(),
                         encoding: "base58"
                     }
@@ -386,18 +457,17 @@ export async function fetchInvestorPositions(connection: Connection, investor: P
 
         return accounts.map(acc => {
             // Manual parsing of PositionAccount data buffer
-            // Skip discriminator (8)
             const data = acc.account.data;
-            const owner = new PublicKey(data.slice(8, 40));
-            // company_id is u64 at offset 40 (8+32)
-            const companyId = new BN(data.slice(40, 48), 'le').toNumber();
-            // encrypted_shares is Euint128 (16 bytes) at offset 48 (40+8)
-            const encryptedShares = data.slice(48, 64);
-            const bump = data[64];
+            const owner = new PublicKey(data.slice(POSITION_OWNER_OFFSET, POSITION_COMPANY_ID_OFFSET));
+            const companyId = new BN(data.slice(POSITION_COMPANY_ID_OFFSET, POSITION_SHARES_OFFSET), 'le').toNumber();
+            const encryptedShares = data.slice(POSITION_SHARES_OFFSET, POSITION_BUMP_OFFSET);
+            const bump = data[POSITION_BUMP_OFFSET];
+            const classId = data[POSITION_CLASS_ID_OFFSET];
 
             return {
                 owner,
                 companyId,
+                classId,
                 encryptedShares,
                 bump,
                 publicKey: acc.pubkey
@@ -410,12 +480,8 @@ export async function fetchInvestorPositions(connection: Connection, investor: P
 }
 
 /**
- * Fetches all investors (PositionAccounts) for a specific company.
- * Note: Since we don't have a getProgramAccounts filter setup perfectly for encrypted data,
- * we will filter by discriminator and check the company_id field (offset 8+32=40 usually, but let's check struct).
- * PositionStruct: owner(32) + company_id(8) + encrypted_shares(16) + bump(1)
- * Actually: Account discriminator (8) + owner(32) + company_id(8)...
- * So company_id is at offset 8+32 = 40.
+ * Fetches all investors (PositionAccounts) for a specific company,
+ * filtering by discriminator and the company_id field.
  */
 export async function fetchCompanyInvestors(connection: Connection, companyId: number): Promise<any[]> {
     try {
@@ -423,7 +489,7 @@ export async function fetchCompanyInvestors(connection: Connection, companyId: n
             filters: [
                 {
                     memcmp: {
-                        offset: 40, // Discriminator (8) + Owner (32)
+                        offset: POSITION_COMPANY_ID_OFFSET,
                         bytes: new BN(companyId).toArrayLike(Buffer, 'le', 8).toString('base64'),
                         encoding: "base64"
                     }
@@ -441,7 +507,7 @@ export async function fetchCompanyInvestors(connection: Connection, companyId: n
 
         return accounts.map(acc => ({
             pubkey: acc.pubkey,
-            owner: new PublicKey(acc.account.data.slice(8, 40))
+            owner: new PublicKey(acc.account.data.slice(POSITION_OWNER_OFFSET, POSITION_COMPANY_ID_OFFSET))
         }));
     } catch (e) {
         console.error("Error fetching investors:", e);
@@ -466,7 +532,7 @@ export async function fetchCompanyByAdmin(connection: Connection, admin: PublicK
                 },
                 {
                     memcmp: {
-                        offset: 16, // Discriminator (8) + company_id (8)
+                        offset: COMPANY_ADMIN_OFFSET,
                         bytes: admin.toBuffer().toString('base64'),
                         encoding: "base64"
                     }
@@ -513,32 +579,66 @@ export async function fetchAllCompanies(connection: Connection): Promise<any[]>
 }
 
 /**
- * Fetches all active trading offers from the program.
+ * Fetches all active public trading offers from the program.
+ * Directed offers (named counterparty or allowlist) are left out.
  */
 export async function fetchAllOffers(program: Program<any>) {
     try {
         const offers = await (program.account as any).offerAccount.all();
         console.log(`[DonaTrade] Fetched ${offers.length} offers`);
-        return offers.map((o: any) => ({
-            ...o.account,
-            pda: o.publicKey
-        }));
+        return offers
+            .filter((o: any) => !isDirectedOffer(o.account))
+            .map((o: any) => ({
+                ...o.account,
+                pda: o.publicKey
+            }));
     } catch (e) {
         console.error("Error fetching all offers:", e);
         return [];
     }
 }
 
+function isDirectedOffer(offer: any): boolean {
+    const counterparty = offer.counterparty as PublicKey | undefined;
+    return (counterparty !== undefined && !counterparty.equals(PublicKey.default))
+        || (offer.allowlistCount ?? 0) > 0;
+}
+
+/**
+ * Fetches donors who opted in to the public leaderboard for a company,
+ * largest published total first.
+ */
+export async function fetchDonorLeaderboard(program: Program<any>, companyId: number) {
+    try {
+        const records = await (program.account as any).donorRecord.all();
+        return records
+            .map((r: any) => r.account)
+            .filter((r: any) => r.public && Number(r.companyId) === companyId)
+            .sort((a: any, b: any) => (b.publicTotal as BN).cmp(a.publicTotal as BN))
+            .map((r: any) => ({
+                donor: r.donor as PublicKey,
+                total: BigInt(r.publicTotal.toString()),
+                donationCount: Number(r.donationCount),
+            }));
+    } catch (e) {
+        console.error("Error fetching donor leaderboard:", e);
+        return [];
+    }
+}
+
 export { TOKEN_PROGRAM_ID, SystemProgram };
 
 export function buildWithdrawCompanyFundsTx(
     program: Program,
     admin: PublicKey,
     companyId: bigint,
-    amount: bigint
+    amount: bigint,
+    mint: PublicKey = USDC_MINT,
+    tokenProgram: PublicKey = TOKEN_PROGRAM_ID
 ) {
     const [companyAccount] = getCompanyPDA(Number(companyId));
     const [globalVault] = getGlobalVaultPDA();
+    const [quoteMint] = getQuoteMintPDA(mint);
 
     // Derived tokens should be fetched outside if needed, but for builder we just need the addresses
     return (program.methods as any).withdrawCompanyFunds(new BN(amount.toString()))
@@ -546,8 +646,10 @@ export function buildWithdrawCompanyFundsTx(
             companyAdmin: admin,
             companyAccount,
             globalVault,
+            quoteMint,
+            mint,
             incoLightningProgram: INCO_LIGHTNING_ID,
-            tokenProgram: TOKEN_PROGRAM_ID,
+            tokenProgram,
             systemProgram: SystemProgram.programId,
         });
 }
@@ -6,25 +6,87 @@
 //! and currency balances on-chain using encrypted state via INCO Lightning.
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use inco_lightning::cpi::accounts::{Allow, Operation};
-use inco_lightning::cpi::{allow, as_euint128, e_add, e_mul, e_sub};
-use inco_lightning::types::Euint128;
+use anchor_lang::solana_program::sysvar::instructions::ID as INSTRUCTIONS_SYSVAR_ID;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use inco_lightning::cpi::accounts::{Allow, Operation, VerifySignature};
+use inco_lightning::cpi::{
+    allow, as_euint128, e_add, e_eq, e_ge, e_mul, e_select, e_shr, e_sub, is_validsignature,
+};
+use inco_lightning::types::{Ebool, Euint128};
 use inco_lightning::ID as INCO_LIGHTNING_ID;
 
 declare_id!("8Tn6H8J7VwE6G3asXS2L6AZcA4y6TMHTRjFZBMjMLvbX");
 
+/// Maximum number of signers in a company multisig.
+pub const MAX_COMPANY_SIGNERS: usize = 5;
+
+/// Class id of a company's built-in common stock, tracked on `CompanyAccount` itself.
+pub const COMMON_CLASS_ID: u8 = 0;
+
+/// Maximum number of share classes an exit waterfall can pay out to.
+pub const MAX_EXIT_CLASSES: usize = 8;
+
+/// Maximum number of buyers on a directed offer's allowlist.
+pub const MAX_OFFER_ALLOWLIST: usize = 8;
+
+/// Maximum number of price levels on a sealed-bid auction's price ladder.
+pub const MAX_AUCTION_PRICE_LEVELS: usize = 8;
+
+/// Maximum number of distinct price levels per side of an order book.
+pub const ORDER_BOOK_MAX_LEVELS: usize = 32;
+
+/// Maximum number of resting orders (both sides) in an order book.
+pub const ORDER_BOOK_MAX_ORDERS: usize = 256;
+
+/// Null link in the order book's intrusive FIFO lists.
+pub const NIL: u16 = u16::MAX;
+
+/// Length of a velocity-limit window in seconds (24h).
+pub const VELOCITY_WINDOW: i64 = 86_400;
+
+/// Time after a tender offer closes during which every tender must be settled before the
+/// offer can be finalized anyway (7 days). Tenders settled later are returned unbought.
+pub const TENDER_SETTLEMENT_GRACE: i64 = 7 * 86_400;
+
+/// Serialized size of an Inco handle; `Euint128` and `Ebool` wrap a u128 and do not
+/// implement `Space`, so accounts holding them size themselves by hand.
+pub const HANDLE_SPACE: usize = 16;
+
+/// Layout version written into every versioned state account.
+/// Accounts created before versioning are treated as version 1.
+pub const CURRENT_ACCOUNT_VERSION: u8 = 3;
+
 #[account]
 #[derive(Default)]
 pub struct InvestorVault {
+    pub version: u8,
     pub owner: Pubkey,
     pub cusd: Euint128,
     pub bump: u8,
+    // Quote mint `cusd` is denominated in (default = USDC); takes the old reserved space
+    pub mint: Pubkey,
+    // Velocity tracking: token units withdrawn and primary purchases in the current window
+    pub withdrawals: VelocityCounter,
+    pub purchases: VelocityCounter,
+    pub velocity_override: Option<VelocityLimits>, // Replaces the platform limits when set
+}
+
+impl Space for InvestorVault {
+    const INIT_SPACE: usize = 1
+        + 32
+        + HANDLE_SPACE
+        + 1
+        + 32
+        + VelocityCounter::INIT_SPACE
+        + VelocityCounter::INIT_SPACE
+        + 1
+        + VelocityLimits::INIT_SPACE;
 }
 
 #[account]
 #[derive(Default)]
 pub struct CompanyAccount {
+    pub version: u8,
     pub company_id: u64,
     pub company_admin: Pubkey,
     pub cusd: Euint128,
@@ -35,22 +97,142 @@ pub struct CompanyAccount {
     // Plaintext Price
     pub price_per_share: u64,
 
+    // Legal agreement investors must accept before acquiring shares
+    pub legal_agreement_hash: [u8; 32],
+    pub legal_agreement_version: u32,
+
+    // Multisig: when threshold > 0, sensitive actions need `threshold` signer approvals
+    pub signers: [Pubkey; MAX_COMPANY_SIGNERS],
+    pub signer_count: u8,
+    pub threshold: u8,
+    // Bumped whenever the signer set changes, invalidating open proposals
+    pub signer_epoch: u32,
+
+    // Emergency stop for this company only
+    pub paused: bool,
+
     pub active: bool,
     pub bump: u8,
+    // Common shares held by investors (positions and their escrows); takes half the old
+    // reserved space, so companies from before it was tracked read the zero handle
+    pub outstanding_shares: Euint128,
+    pub reserved: [u8; 16], // Room for new fields without a realloc
+
+    // --- v3 fields, appended after the v2 layout ---
+
+    // Private pricing: when set, the price lives only in `encrypted_price`
+    // and `price_per_share` is kept at zero
+    pub private_pricing: bool,
+    pub encrypted_price: Euint128,
+
+    // Set for good once the company is acquired and its exit proceeds are declared
+    pub exited: bool,
+
+    // Auditor / transfer agent with standing read access (default = none)
+    pub auditor: Pubkey,
+
+    // How primary shares are currently sold
+    pub offering_mode: OfferingMode,
+    pub dutch_schedule: DutchSchedule,
+    // Pro-rata rights for existing holders on the latest supply increase
+    pub rights_period: RightsPeriod,
+
+    // Quote mint `cusd` and prices are denominated in (default = USDC)
+    pub quote_mint: Pubkey,
+
+    // Velocity tracking: token units withdrawn and primary sales in the current window
+    pub withdrawals: VelocityCounter,
+    pub primary_sales: VelocityCounter,
+    pub velocity_override: Option<VelocityLimits>, // Replaces the platform limits when set
+
+    // Secondary order book (default = none)
+    pub order_book: Pubkey,
+}
+
+impl Space for CompanyAccount {
+    const INIT_SPACE: usize = 1
+        + 8
+        + 32
+        + HANDLE_SPACE
+        + HANDLE_SPACE
+        + 8
+        + 32
+        + 4
+        + MAX_COMPANY_SIGNERS * 32
+        + 1
+        + 1
+        + 4
+        + 1
+        + 1
+        + 1
+        + HANDLE_SPACE
+        + 16
+        + 1
+        + HANDLE_SPACE
+        + 1
+        + 32
+        + OfferingMode::INIT_SPACE
+        + DutchSchedule::INIT_SPACE
+        + RightsPeriod::INIT_SPACE
+        + 32
+        + VelocityCounter::INIT_SPACE
+        + VelocityCounter::INIT_SPACE
+        + 1
+        + VelocityLimits::INIT_SPACE
+        + 32;
+}
+
+impl CompanyAccount {
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.exited, DonatradeError::CompanyExited);
+        require!(!self.paused, DonatradeError::CompanyPaused);
+        Ok(())
+    }
+
+    /// Plaintext unit price for primary trades at `now`.
+    pub fn current_price(&self, now: i64) -> u64 {
+        match self.offering_mode {
+            OfferingMode::Dutch => self.dutch_schedule.price_at(now),
+            _ => self.price_per_share,
+        }
+    }
+
+    pub fn auditor(&self) -> Option<Pubkey> {
+        (self.auditor != Pubkey::default()).then_some(self.auditor)
+    }
+
+    pub fn multisig_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    pub fn signer_index(&self, key: &Pubkey) -> Option<usize> {
+        self.signers[..self.signer_count as usize]
+            .iter()
+            .position(|signer| signer == key)
+    }
 }
 
 #[account]
 #[derive(Default)]
 pub struct PositionAccount {
+    pub version: u8,
     pub owner: Pubkey,
     pub company_id: u64,
     pub encrypted_shares: Euint128,
     pub bump: u8,
+    // Share class held (common = 0); takes the first reserved byte, so v2 positions read as common
+    pub class_id: u8,
+    pub reserved: [u8; 31], // Room for new fields without a realloc
+}
+
+impl Space for PositionAccount {
+    const INIT_SPACE: usize = 1 + 32 + 8 + HANDLE_SPACE + 1 + 1 + 31;
 }
 
 #[account]
 #[derive(Default)]
 pub struct OfferAccount {
+    pub version: u8,
     pub offer_id: u64,
     pub seller: Pubkey,
     pub company_id: u64,
@@ -59,707 +241,7872 @@ pub struct OfferAccount {
     pub price_per_share: u64,
     pub is_active: bool,
     pub bump: u8,
+    pub reserved: [u8; 32], // Room for new fields without a realloc
+
+    // --- v3 fields, appended after the v2 layout ---
+    pub class_id: u8,
+    pub private_pricing: bool,
+    pub encrypted_price: Euint128, // Used instead of price_per_share when private_pricing
+
+    // Directed offers: only the counterparty or an allowlisted buyer may take them,
+    // and they are left out of public listings. Empty = open to anyone.
+    pub counterparty: Pubkey,
+    pub allowlist: [Pubkey; MAX_OFFER_ALLOWLIST],
+    pub allowlist_count: u8,
+}
+
+impl Space for OfferAccount {
+    const INIT_SPACE: usize = 1
+        + 8
+        + 32
+        + 8
+        + 8
+        + HANDLE_SPACE
+        + 8
+        + 1
+        + 1
+        + 32
+        + 1
+        + 1
+        + HANDLE_SPACE
+        + 32
+        + MAX_OFFER_ALLOWLIST * 32
+        + 1;
+}
+
+impl OfferAccount {
+    pub fn is_directed(&self) -> bool {
+        self.counterparty != Pubkey::default() || self.allowlist_count > 0
+    }
+
+    pub fn may_buy(&self, buyer: &Pubkey) -> bool {
+        !self.is_directed()
+            || self.counterparty == *buyer
+            || self.allowlist[..self.allowlist_count as usize].contains(buyer)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ShareClassKind {
+    Common,
+    Preferred,
 }
 
+/// An additional share class with its own pool, price and transfer rules.
+/// Class 0 is the company's common stock on `CompanyAccount` and has no `ShareClass` account.
 #[account]
-#[derive(Default)]
-pub struct GlobalProgramVault {
-    pub usdc_token_account: Pubkey,
+pub struct ShareClass {
+    pub company_id: u64,
+    pub class_id: u8,
+    pub kind: ShareClassKind,
+    pub shares_available: Euint128,
+    pub price_per_share: u64,
+    pub transferable: bool,
+    pub conversion_ratio: u64, // Common shares received per share converted; 0 = not convertible
+    // Liquidation preference: `price_per_share * liquidation_multiple_bps / 10_000` per share,
+    // paid to higher `seniority` first
+    pub liquidation_multiple_bps: u32,
+    pub participating: bool, // Also shares pro-rata in what is left, as converted
+    pub seniority: u8,
+    pub active: bool,
     pub bump: u8,
+    pub outstanding_shares: Euint128, // Shares of this class held by investors
 }
 
-#[program]
-pub mod donatrade_program {
-    use super::*;
+impl Space for ShareClass {
+    const INIT_SPACE: usize = 8
+        + 1
+        + ShareClassKind::INIT_SPACE
+        + HANDLE_SPACE
+        + 8
+        + 1
+        + 8
+        + 4
+        + 1
+        + 1
+        + 1
+        + 1
+        + HANDLE_SPACE;
+}
 
-    pub fn initialize_global_vault(ctx: Context<InitializeGlobalVault>) -> Result<()> {
-        let vault = &mut ctx.accounts.global_vault;
-        vault.usdc_token_account = ctx.accounts.usdc_token_account.key();
-        vault.bump = ctx.bumps.global_vault;
-        Ok(())
+/// Cumulative encrypted donations from one donor to one company, for tax receipts.
+/// Donors may opt in to the public leaderboard by publishing an attested total.
+#[account]
+pub struct DonorRecord {
+    pub donor: Pubkey,
+    pub company_id: u64,
+    pub total: Euint128,
+    pub donation_count: u64,
+    pub public: bool,
+    pub public_total: u64, // Last published total; may lag `total` until republished
+    pub bump: u8,
+}
+
+impl Space for DonorRecord {
+    const INIT_SPACE: usize = 32 + 8 + HANDLE_SPACE + 8 + 1 + 8 + 1;
+}
+
+/// Company buyback: up to `max_shares` common shares at a fixed price during a window.
+/// The full budget is escrowed from `company.cusd` up front; unspent cUSD returns at finalization.
+#[account]
+pub struct TenderOffer {
+    pub company_id: u64,
+    pub tender_id: u64,
+    pub max_shares: u64,
+    pub price_per_share: u64,
+    pub closes_at: i64,
+    pub retire: bool, // Cancel repurchased shares instead of returning them to the pool
+    pub total_tendered: Euint128,
+    pub repurchased: Euint128,
+    pub escrow: Euint128, // Budget not yet paid out
+    pub tender_count: u64,
+    pub settled_count: u64,
+    pub finalized: bool,
+    pub bump: u8,
+    pub tendered_shares: u64, // Plaintext `total_tendered`, once revealed after the window
+    pub total_revealed: bool,
+}
+
+impl Space for TenderOffer {
+    const INIT_SPACE: usize =
+        8 + 8 + 8 + 8 + 8 + 1 + HANDLE_SPACE + HANDLE_SPACE + HANDLE_SPACE + 8 + 8 + 1 + 1;
+}
+
+/// A holder's encrypted shares tendered into a `TenderOffer`.
+#[account]
+pub struct Tender {
+    pub holder: Pubkey,
+    pub tender_offer: Pubkey,
+    pub amount: Euint128,
+    pub bump: u8,
+}
+
+impl Space for Tender {
+    const INIT_SPACE: usize = 32 + 32 + HANDLE_SPACE + 1;
+}
+
+/// Per-share exit payouts computed by the waterfall when a company is acquired.
+#[account]
+pub struct ExitEvent {
+    pub company_id: u64,
+    pub proceeds: u64,
+    pub class_ids: [u8; MAX_EXIT_CLASSES],
+    pub payout_per_share: [u64; MAX_EXIT_CLASSES],
+    pub class_count: u8,
+    pub declared_at: i64,
+    pub quote_mint: Pubkey,
+    pub bump: u8,
+    /// Escrowed proceeds not yet claimed; caps the total paid out
+    pub unclaimed: Euint128,
+}
+
+impl Space for ExitEvent {
+    const INIT_SPACE: usize =
+        8 + 8 + MAX_EXIT_CLASSES + MAX_EXIT_CLASSES * 8 + 1 + 8 + 32 + 1 + HANDLE_SPACE;
+}
+
+impl ExitEvent {
+    pub fn payout_for(&self, class_id: u8) -> Option<u64> {
+        self.class_ids[..self.class_count as usize]
+            .iter()
+            .position(|id| *id == class_id)
+            .map(|i| self.payout_per_share[i])
     }
+}
 
-    /// Admin-only: Activate a company that was approved off-chain.
-    /// Creates the on-chain CompanyAccount with encrypted financial state.
-    pub fn activate_company(
-        ctx: Context<ActivateCompany>,
-        company_id: u64,
-        company_admin: Pubkey,
-        initial_shares: u64,
-        price_per_share: u64,
-    ) -> Result<()> {
-        let company = &mut ctx.accounts.company_account;
-        company.company_id = company_id;
-        company.company_admin = company_admin;
+/// Outstanding share count of one class for an exit, as decrypted from its tracked count.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct ExitClassShares {
+    pub class_id: u8,
+    pub outstanding: u64,
+}
 
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let admin = ctx.accounts.platform_admin.to_account_info();
+/// One class's claim on exit proceeds, as seen by the waterfall.
+#[derive(Clone, Copy, Debug)]
+pub struct Tranche {
+    pub outstanding: u64,
+    pub preference_per_share: u64,
+    pub seniority: u8,
+    pub participating: bool,
+    pub as_converted: u64, // Common-equivalent shares per share when participating
+}
 
-        // Initialize shares available as encrypted
-        company.shares_available = as_euint128(
-            CpiContext::new(inco_program, Operation { signer: admin }),
-            initial_shares as u128,
-        )?;
+/// Share-for-share swap: the maker's shares of one company held in escrow
+/// against a requested quantity of another company's shares.
+#[account]
+pub struct SwapOffer {
+    pub maker: Pubkey,
+    pub swap_id: u64,
+    pub give_company_id: u64,
+    pub escrowed_shares: Euint128, // Maker's shares of the give company
+    pub want_company_id: u64,
+    pub want_shares: Euint128, // Shares of the want company asked in return
+    pub bump: u8,
+}
 
-        company.price_per_share = price_per_share;
-        company.active = true;
-        company.bump = ctx.bumps.company_account;
-        Ok(())
+impl Space for SwapOffer {
+    const INIT_SPACE: usize = 32 + 8 + 8 + HANDLE_SPACE + 8 + HANDLE_SPACE + 1;
+}
+
+/// Terms of a company's SAFE round. Converts once the company sets a priced round.
+#[account]
+#[derive(InitSpace)]
+pub struct SafeRound {
+    pub company_id: u64,
+    pub round_id: u64,
+    pub valuation_cap: u64, // 0 = uncapped
+    pub discount_bps: u16,
+    // Price per share SAFEs convert at; 0 while the round is still raising
+    pub conversion_price: u64,
+    pub bump: u8,
+}
+
+impl SafeRound {
+    pub fn is_priced(&self) -> bool {
+        self.conversion_price > 0
     }
 
-    pub fn deposit<'info>(
-        ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
-        amount: u64,
-    ) -> Result<()> {
-        // 1. Transfer USDC from investor to vault
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.investor_token_account.to_account_info(),
-                    to: ctx.accounts.vault_token_account.to_account_info(),
-                    authority: ctx.accounts.investor.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
+    /// Better (lower) of the capped and the discounted price for a priced round.
+    pub fn conversion_price_for(&self, round_price: u64, fully_diluted_shares: u64) -> u64 {
+        let discounted = round_price as u128 * (10_000 - self.discount_bps as u128) / 10_000;
+        let mut price = discounted as u64;
+        if self.valuation_cap > 0 {
+            price = price.min(self.valuation_cap / fully_diluted_shares);
+        }
+        price.max(1)
+    }
+}
 
-        // 2. Encrypt and add to vault balance
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let investor = ctx.accounts.investor.to_account_info();
+/// An investor's SAFE in a round: the encrypted cUSD invested so far.
+#[account]
+pub struct Safe {
+    pub investor: Pubkey,
+    pub company_id: u64,
+    pub round_id: u64,
+    pub principal: Euint128,
+    pub bump: u8,
+}
 
-        let e_amount = as_euint128(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            amount as u128,
-        )?;
+impl Space for Safe {
+    const INIT_SPACE: usize = 32 + 8 + 8 + HANDLE_SPACE + 1;
+}
 
-        let vault = &mut ctx.accounts.investor_vault;
-        vault.owner = ctx.accounts.investor.key();
-        vault.bump = ctx.bumps.investor_vault; // Store bump
+/// Shares reserved from a company's pool for employee option grants.
+#[account]
+pub struct OptionPool {
+    pub company_id: u64,
+    pub available: Euint128, // Reserved shares not yet granted
+    pub bump: u8,
+}
 
-        // Handle first deposit: if current handle is 0, we must still use e_add
-        // to ensure the resulting handle is a valid ciphertext for Inco's storage.
-        // We cannot just assign 'e_amount' because it's a lifted plaintext.
-        if vault.cusd.0 == 0 {
-            // Create an encrypted zero
-            let e_zero = as_euint128(
-                CpiContext::new(
-                    inco_program.clone(),
-                    Operation {
-                        signer: investor.clone(),
-                    },
-                ),
-                0,
-            )?;
+impl Space for OptionPool {
+    const INIT_SPACE: usize = 8 + HANDLE_SPACE + 1;
+}
 
-            vault.cusd = e_add(
-                CpiContext::new(
-                    inco_program.clone(),
-                    Operation {
-                        signer: investor.clone(),
-                    },
-                ),
-                e_zero,
-                e_amount,
-                0,
-            )?;
+/// Employee stock options: encrypted grant size, public strike and schedule.
+#[account]
+pub struct OptionGrant {
+    pub employee: Pubkey,
+    pub company_id: u64,
+    pub grant_id: u64,
+    pub total: Euint128,
+    pub exercised: Euint128,
+    pub strike_price: u64,
+    // Linear vesting from `vesting_start` to `vesting_end`, nothing before `cliff_at`
+    pub vesting_start: i64,
+    pub cliff_at: i64,
+    pub vesting_end: i64,
+    pub expires_at: i64,
+    pub early_exercise: bool, // Unvested options may be exercised while employed
+    pub terminated_at: i64,   // 0 while employed; vesting stops here
+    pub post_termination_window: i64, // Seconds after termination options stay exercisable
+    pub bump: u8,
+}
+
+impl Space for OptionGrant {
+    const INIT_SPACE: usize =
+        32 + 8 + 8 + HANDLE_SPACE + HANDLE_SPACE + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 1;
+}
+
+impl OptionGrant {
+    pub fn is_terminated(&self) -> bool {
+        self.terminated_at != 0
+    }
+
+    pub fn exercise_open(&self, now: i64) -> bool {
+        now < self.expires_at
+            && (!self.is_terminated() || now <= self.terminated_at + self.post_termination_window)
+    }
+
+    /// Time vesting is measured at: `now`, or the termination time once terminated.
+    pub fn vesting_cutoff(&self, now: i64) -> i64 {
+        if self.is_terminated() {
+            now.min(self.terminated_at)
         } else {
-            vault.cusd = e_add(
-                CpiContext::new(
-                    inco_program.clone(),
-                    Operation {
-                        signer: investor.clone(),
-                    },
-                ),
-                vault.cusd,
-                e_amount,
-                0,
-            )?;
+            now
         }
+    }
+}
 
-        Ok(())
+/// Proof that an investor signed a specific version of a company's legal agreement.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct AgreementAcceptance {
+    pub investor: Pubkey,
+    pub company_id: u64,
+    pub agreement_hash: [u8; 32],
+    pub agreement_version: u32,
+    pub accepted_at: i64,
+    pub bump: u8,
+}
+
+impl AgreementAcceptance {
+    /// True if this acceptance matches the company's current agreement.
+    pub fn covers(&self, company: &CompanyAccount) -> bool {
+        self.company_id == company.company_id
+            && self.agreement_version == company.legal_agreement_version
+            && self.agreement_hash == company.legal_agreement_hash
     }
+}
 
-    pub fn buy_shares<'info>(
-        ctx: Context<'_, '_, '_, 'info, BuyShares<'info>>,
-        e_shares: Euint128,
-    ) -> Result<()> {
-        let company = &mut ctx.accounts.company_account;
-        require!(company.active, DonatradeError::Inactive);
+/// Pending request to close an empty vault or position.
+/// Holds the encrypted `balance == 0` check that must be attested as true before closing.
+#[account]
+pub struct CloseRequest {
+    pub owner: Pubkey,
+    pub target: Pubkey,
+    pub balance_handle: u128, // Balance the check was computed against
+    pub zero_check: Ebool,
+    pub bump: u8,
+}
 
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let investor = ctx.accounts.investor.to_account_info();
+impl Space for CloseRequest {
+    const INIT_SPACE: usize = 32 + 32 + 16 + HANDLE_SPACE + 1;
+}
 
-        // 1. Subtract shares from available pool (Encrypted check happens in e_sub)
-        company.shares_available = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            company.shares_available,
-            e_shares,
-            0,
-        )?;
+/// How a company currently sells primary shares.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum OfferingMode {
+    /// `buy_shares` at `price_per_share`
+    #[default]
+    FixedPrice,
+    /// A sealed-bid auction is running; `buy_shares` is closed
+    SealedBid,
+    /// `buy_shares` at the descending price from `dutch_schedule`
+    Dutch,
+}
 
-        // 2. Convert Price to Encrypted for calculation
-        let e_price = as_euint128(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            company.price_per_share as u128,
-        )?;
+/// Linear price decay for a Dutch offering, evaluated against `Clock` at execution time.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DutchSchedule {
+    pub start_price: u64,
+    pub floor_price: u64,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    // Pending `shares_available == 0` check for ending early
+    pub exhausted_check: Ebool,
+    pub exhausted_check_of: u128, // shares_available handle the check was computed against
+}
 
-        // 3. Calculate Cost (Price * Amount) via e_mul
-        let e_cost = e_mul(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            e_price,
-            e_shares,
-            0,
-        )?;
+impl Space for DutchSchedule {
+    const INIT_SPACE: usize = 8 + 8 + 8 + 8 + HANDLE_SPACE + 16;
+}
 
-        // 4. Subtract Cost from Investor Vault
-        ctx.accounts.investor_vault.cusd = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            ctx.accounts.investor_vault.cusd,
-            e_cost,
-            0,
-        )?;
+impl DutchSchedule {
+    /// Price at `now`: start price before the window, floor after it, linear in between.
+    pub fn price_at(&self, now: i64) -> u64 {
+        if now <= self.starts_at {
+            return self.start_price;
+        }
+        if now >= self.ends_at {
+            return self.floor_price;
+        }
+        let elapsed = (now - self.starts_at) as u128;
+        let window = (self.ends_at - self.starts_at) as u128;
+        let drop = (self.start_price - self.floor_price) as u128 * elapsed / window;
+        self.start_price - drop as u64
+    }
+}
 
-        // 5. Add Cost to Company Balance
-        company.cusd = e_add(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            company.cusd,
-            e_cost,
-            0,
-        )?;
+/// First-refusal window on new common supply: until `ends_at` only holders with a
+/// `RightsEntitlement` for `round` may buy, up to `new_shares * held / outstanding_shares`.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct RightsPeriod {
+    pub round: u64,
+    pub new_shares: u64,
+    pub outstanding_shares: u64, // Snapshot denominator supplied by the company
+    pub ends_at: i64,
+}
 
-        // 6. Update Position
-        ctx.accounts.position.owner = ctx.accounts.investor.key();
-        ctx.accounts.position.company_id = company.company_id;
-        ctx.accounts.position.bump = ctx.bumps.position;
-        ctx.accounts.position.encrypted_shares = e_add(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            ctx.accounts.position.encrypted_shares,
-            e_shares,
-            0,
-        )?;
+impl RightsPeriod {
+    pub fn is_open(&self, now: i64) -> bool {
+        now < self.ends_at
+    }
+}
 
-        Ok(())
+/// A holder's encrypted pro-rata allocation for one rights round.
+#[account]
+pub struct RightsEntitlement {
+    pub holder: Pubkey,
+    pub company_id: u64,
+    pub round: u64,
+    pub remaining: Euint128,
+    pub bump: u8,
+}
+
+impl Space for RightsEntitlement {
+    const INIT_SPACE: usize = 32 + 8 + 8 + HANDLE_SPACE + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum AuctionStatus {
+    Open,
+    Closed,
+    Priced,
+    Settled,
+}
+
+/// Sealed-bid primary auction with a uniform clearing price.
+/// Bids are encrypted; only the aggregate demand at the clearing level and the
+/// level above it is ever decrypted.
+#[account]
+pub struct Auction {
+    pub company_id: u64,
+    pub auction_id: u64,
+    pub supply: u64,
+    pub price_levels: [u64; MAX_AUCTION_PRICE_LEVELS], // Ascending price ladder
+    pub level_count: u8,
+    pub demand: [Euint128; MAX_AUCTION_PRICE_LEVELS], // Shares bid at or above each level
+    pub remaining: Euint128,                          // Supply not yet allocated during settlement
+    pub closes_at: i64,
+    pub clearing_price: u64,
+    pub bid_count: u64,
+    pub settled_count: u64,
+    pub status: AuctionStatus,
+    pub bump: u8,
+    pub clearing_level: u8,
+    pub marginal_supply: u64, // Supply left for bids at exactly the clearing level
+    pub marginal_demand: u64, // Shares bid at exactly the clearing level
+}
+
+impl Space for Auction {
+    const INIT_SPACE: usize = 8
+        + 8
+        + 8
+        + MAX_AUCTION_PRICE_LEVELS * 8
+        + 1
+        + MAX_AUCTION_PRICE_LEVELS * HANDLE_SPACE
+        + HANDLE_SPACE
+        + 8
+        + 8
+        + 8
+        + 8
+        + AuctionStatus::INIT_SPACE
+        + 1
+        + 1
+        + 8
+        + 8;
+}
+
+impl Auction {
+    /// Checks revealed cumulative demand at `level` and the level above it against the supply
+    /// and returns `(marginal_supply, marginal_demand)`. `level` clears if demand there meets
+    /// the supply (or it is the floor) and demand one level up falls short.
+    pub fn marginal_split(
+        &self,
+        level: usize,
+        demand_at: u64,
+        demand_above: u64,
+    ) -> Result<(u64, u64)> {
+        require!(
+            level < self.level_count as usize,
+            DonatradeError::InvalidAuctionParams
+        );
+        require!(
+            (level + 1 < self.level_count as usize || demand_above == 0)
+                && demand_above < self.supply
+                && demand_above <= demand_at
+                && (level == 0 || demand_at >= self.supply),
+            DonatradeError::ClearingLevelMismatch
+        );
+        Ok((self.supply - demand_above, demand_at - demand_above))
     }
+}
 
-    pub fn withdraw<'info>(
-        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
-        amount: u64,
-    ) -> Result<()> {
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let investor = ctx.accounts.investor.to_account_info();
+/// An encrypted bid with its cUSD escrow (quantity * max price) held out of the bidder's vault.
+#[account]
+pub struct AuctionBid {
+    pub bidder: Pubkey,
+    pub company_id: u64,
+    pub auction_id: u64,
+    pub sequence: u64, // Submission order
+    pub quantity: Euint128,
+    pub max_price: Euint128,
+    pub escrow: Euint128,
+    pub bump: u8,
+}
 
-        // 1. Subtract from encrypted balance
-        let e_amount = as_euint128(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            amount as u128,
-        )?;
-        ctx.accounts.investor_vault.cusd = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            ctx.accounts.investor_vault.cusd,
-            e_amount,
-            0,
-        )?;
+impl Space for AuctionBid {
+    const INIT_SPACE: usize = 32 + 8 + 8 + 8 + HANDLE_SPACE + HANDLE_SPACE + HANDLE_SPACE + 1;
+}
 
-        // 2. Transfer physical USDC from vault to investor
-        let seeds = &[
-            b"vault_authority".as_ref(),
-            &[ctx.accounts.global_vault.bump],
-        ];
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault_token_account.to_account_info(),
-                    to: ctx.accounts.investor_token_account.to_account_info(),
-                    authority: ctx.accounts.global_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
-            amount,
-        )?;
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
 
-        Ok(())
+impl OrderSide {
+    /// True if price `a` has priority over price `b` on this side.
+    pub fn better(self, a: u64, b: u64) -> bool {
+        match self {
+            OrderSide::Bid => a > b,
+            OrderSide::Ask => a < b,
+        }
     }
+}
 
-    pub fn authorize_decryption(ctx: Context<AuthorizeDecryption>, handle: u128) -> Result<()> {
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let investor = ctx.accounts.investor.to_account_info();
+/// A resting order. Quantities are Inco handles stored as raw `u128` so the book stays `Pod`.
+/// Bids hold `remaining * price` cUSD in escrow; asks hold `remaining` shares.
+#[zero_copy]
+pub struct BookOrder {
+    pub owner: Pubkey,
+    pub remaining: u128,   // Euint128 handle
+    pub empty_check: u128, // Ebool handle for `remaining == 0`, refreshed by every match
+    pub price: u64,
+    pub sequence: u64, // Placement order; the earlier order is the maker
+    pub next: u16,     // Next order at the same price level, or NIL
+    pub side: u8,
+    pub in_use: u8,
+    pub _padding: [u8; 12],
+}
 
-        allow(
-            CpiContext::new(
-                inco_program,
-                Allow {
-                    allowance_account: ctx.accounts.allowance_account.to_account_info(),
-                    signer: investor,
-                    allowed_address: ctx.accounts.allowed_address.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-            ),
-            handle,
-            true,                               // is_encrypted check
-            ctx.accounts.allowed_address.key(), // derived address target
-        )?;
-        Ok(())
+impl BookOrder {
+    pub fn side(&self) -> OrderSide {
+        if self.side == OrderSide::Bid as u8 {
+            OrderSide::Bid
+        } else {
+            OrderSide::Ask
+        }
     }
+}
 
-    pub fn sell_shares<'info>(
-        ctx: Context<'_, '_, '_, 'info, SellShares<'info>>,
-        e_shares: Euint128,
-    ) -> Result<()> {
-        let company = &mut ctx.accounts.company_account;
-        // Don't check plaintext overflow on price.
+/// FIFO queue of orders at one price.
+#[zero_copy]
+pub struct PriceLevel {
+    pub price: u64,
+    pub head: u16,
+    pub tail: u16,
+    pub count: u16,
+    pub _padding: u16,
+}
 
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let investor = ctx.accounts.investor.to_account_info();
+/// Per-company central limit order book with price-time priority.
+/// Too large for a CPI-created account: the client allocates
+/// `8 + size_of::<OrderBook>()` bytes owned by this program and passes it to `initialize_order_book`.
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub company_id: u64,
+    pub next_sequence: u64,
+    pub bid_level_count: u16,
+    pub ask_level_count: u16,
+    pub order_count: u16,
+    pub _padding: [u8; 10],
+    pub bids: [PriceLevel; ORDER_BOOK_MAX_LEVELS], // Best (highest) price first
+    pub asks: [PriceLevel; ORDER_BOOK_MAX_LEVELS], // Best (lowest) price first
+    pub orders: [BookOrder; ORDER_BOOK_MAX_ORDERS],
+}
 
-        // 2. Value = shares * price (lift price to encrypted)
-        let e_price = as_euint128(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            company.price_per_share as u128,
-        )?;
+impl OrderBook {
+    fn levels(&self, side: OrderSide) -> &[PriceLevel] {
+        match side {
+            OrderSide::Bid => &self.bids[..self.bid_level_count as usize],
+            OrderSide::Ask => &self.asks[..self.ask_level_count as usize],
+        }
+    }
 
-        let e_val = e_mul(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            e_shares,
-            e_price,
-            0, // scalar_byte
-        )?;
+    fn levels_mut(
+        &mut self,
+        side: OrderSide,
+    ) -> (&mut [PriceLevel; ORDER_BOOK_MAX_LEVELS], &mut u16) {
+        match side {
+            OrderSide::Bid => (&mut self.bids, &mut self.bid_level_count),
+            OrderSide::Ask => (&mut self.asks, &mut self.ask_level_count),
+        }
+    }
 
-        // Update Position and Company warehouse
-        ctx.accounts.position.encrypted_shares = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            ctx.accounts.position.encrypted_shares,
-            e_shares,
-            0,
-        )?;
+    /// Slot of the oldest order at the best price on `side`.
+    pub fn best(&self, side: OrderSide) -> Option<u16> {
+        self.levels(side).first().map(|level| level.head)
+    }
 
-        company.cusd = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            company.cusd,
-            e_val,
-            0,
-        )?;
+    pub fn order(&self, slot: u16) -> Result<BookOrder> {
+        self.orders
+            .get(slot as usize)
+            .filter(|order| order.in_use != 0)
+            .copied()
+            .ok_or_else(|| error!(DonatradeError::OrderNotFound))
+    }
 
-        // Add shares back to company (Private pool)
-        company.shares_available = e_add(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: investor.clone(),
-                },
-            ),
-            company.shares_available,
-            e_shares,
-            0,
-        )?;
+    /// Appends an order to the back of its price level, creating the level if needed.
+    pub fn insert_order(
+        &mut self,
+        side: OrderSide,
+        owner: Pubkey,
+        price: u64,
+        remaining: u128,
+    ) -> Result<u16> {
+        let slot = self
+            .orders
+            .iter()
+            .position(|order| order.in_use == 0)
+            .ok_or(DonatradeError::OrderBookFull)? as u16;
+
+        let prev_tail = {
+            let (levels, level_count) = self.levels_mut(side);
+            let count = *level_count as usize;
+            let index = levels[..count]
+                .iter()
+                .position(|level| !side.better(level.price, price))
+                .unwrap_or(count);
+            if index == count || levels[index].price != price {
+                require!(count < ORDER_BOOK_MAX_LEVELS, DonatradeError::OrderBookFull);
+                levels.copy_within(index..count, index + 1);
+                levels[index] = PriceLevel {
+                    price,
+                    head: NIL,
+                    tail: NIL,
+                    count: 0,
+                    _padding: 0,
+                };
+                *level_count += 1;
+            }
+            let level = &mut levels[index];
+            let prev_tail = level.tail;
+            if level.head == NIL {
+                level.head = slot;
+            }
+            level.tail = slot;
+            level.count += 1;
+            prev_tail
+        };
+        if prev_tail != NIL {
+            self.orders[prev_tail as usize].next = slot;
+        }
+
+        self.orders[slot as usize] = BookOrder {
+            owner,
+            remaining,
+            empty_check: 0,
+            price,
+            sequence: self.next_sequence,
+            next: NIL,
+            side: side as u8,
+            in_use: 1,
+            _padding: [0; 12],
+        };
+        self.next_sequence += 1;
+        self.order_count += 1;
+        Ok(slot)
+    }
+
+    /// Unlinks an order from its price level, dropping the level once it is empty.
+    pub fn remove_order(&mut self, slot: u16) -> Result<()> {
+        let order = self.order(slot)?;
+        let side = order.side();
+        let index = self
+            .levels(side)
+            .iter()
+            .position(|level| level.price == order.price)
+            .ok_or(DonatradeError::OrderNotFound)?;
+
+        let mut prev = NIL;
+        let mut cursor = self.levels(side)[index].head;
+        while cursor != slot {
+            require!(cursor != NIL, DonatradeError::OrderNotFound);
+            prev = cursor;
+            cursor = self.orders[cursor as usize].next;
+        }
+        if prev != NIL {
+            self.orders[prev as usize].next = order.next;
+        }
+
+        let (levels, level_count) = self.levels_mut(side);
+        let level = &mut levels[index];
+        if level.head == slot {
+            level.head = order.next;
+        }
+        if level.tail == slot {
+            level.tail = prev;
+        }
+        level.count -= 1;
+        if level.count == 0 {
+            let count = *level_count as usize;
+            levels.copy_within(index + 1..count, index);
+            *level_count -= 1;
+        }
+
+        self.orders[slot as usize].in_use = 0;
+        self.orders[slot as usize].next = NIL;
+        self.order_count -= 1;
         Ok(())
     }
+}
 
-    pub fn update_offering(
-        ctx: Context<UpdateOffering>,
+/// A sensitive company action that needs multisig approval once a signer set is configured.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub enum CompanyAction {
+    WithdrawFunds {
+        amount: u64,
+        destination: Pubkey,
+    },
+    UpdateOffering {
         new_price: u64,
         add_shares: u64,
         active: bool,
-    ) -> Result<()> {
-        let company = &mut ctx.accounts.company_account;
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let admin = ctx.accounts.company_admin.to_account_info();
+        rights_period: i64,
+        outstanding_shares: u64,
+    },
+    SetPrivatePrice {
+        e_price: u128,
+    },
+    RotateAdmin {
+        new_admin: Pubkey,
+    },
+    CreateTenderOffer {
+        tender_id: u64,
+        max_shares: u64,
+        price_per_share: u64,
+        closes_at: i64,
+        retire: bool,
+    },
+    UpdateShareClass {
+        class_id: u8,
+        price_per_share: u64,
+        add_shares: u64,
+        transferable: bool,
+        active: bool,
+    },
+    SetSigners {
+        signers: [Pubkey; MAX_COMPANY_SIGNERS],
+        signer_count: u8,
+        threshold: u8,
+    },
+    CreateAuction {
+        auction_id: u64,
+        supply: u64,
+        #[max_len(MAX_AUCTION_PRICE_LEVELS)]
+        price_levels: Vec<u64>,
+        closes_at: i64,
+    },
+    SetAuditor {
+        auditor: Pubkey,
+    },
+    CreateSafeRound {
+        round_id: u64,
+        valuation_cap: u64,
+        discount_bps: u16,
+    },
+    PriceSafeRound {
+        round_id: u64,
+        fully_diluted_shares: u64,
+    },
+    FundOptionPool {
+        amount: u64,
+    },
+    IssueOptionGrant {
+        grant_id: u64,
+        employee: Pubkey,
+        e_total: u128,
+        strike_price: u64,
+        vesting_start: i64,
+        cliff_at: i64,
+        vesting_end: i64,
+        expires_at: i64,
+        early_exercise: bool,
+        post_termination_window: i64,
+    },
+    StartDutchAuction {
+        start_price: u64,
+        floor_price: u64,
+        starts_at: i64,
+        ends_at: i64,
+    },
+    EndDutchAuction,
+    CreateShareClass {
+        class_id: u8,
+        kind: ShareClassKind,
+        conversion_ratio: u64,
+        liquidation_multiple_bps: u32,
+        participating: bool,
+        seniority: u8,
+    },
+    DeclareExit {
+        proceeds: u64,
+    },
+    TerminateOptionGrant {
+        employee: Pubkey,
+        grant_id: u64,
+    },
+    ReclaimOptionGrant {
+        employee: Pubkey,
+        grant_id: u64,
+    },
+    UpdateLegalAgreement {
+        legal_agreement_hash: [u8; 32],
+    },
+    FinalizeAuctionPrice {
+        auction_id: u64,
+        level: u8,
+    },
+    InitializeOrderBook {
+        order_book: Pubkey,
+    },
+    RecoverAdmin {
+        new_admin: Pubkey,
+    },
+}
 
-        // Update price (plaintext)
-        company.price_per_share = new_price;
+impl CompanyAction {
+    /// Builds a validated SetSigners action from a list of signers.
+    pub fn set_signers(signers: &[Pubkey], threshold: u8) -> Result<Self> {
+        require!(
+            signers.len() <= MAX_COMPANY_SIGNERS
+                && threshold as usize <= signers.len()
+                && (signers.is_empty() || threshold > 0),
+            DonatradeError::InvalidSignerSet
+        );
+        let mut padded = [Pubkey::default(); MAX_COMPANY_SIGNERS];
+        for (i, signer) in signers.iter().enumerate() {
+            require!(
+                *signer != Pubkey::default() && !signers[..i].contains(signer),
+                DonatradeError::InvalidSignerSet
+            );
+            padded[i] = *signer;
+        }
+        Ok(CompanyAction::SetSigners {
+            signers: padded,
+            signer_count: signers.len() as u8,
+            threshold,
+        })
+    }
+}
 
-        // Add shares (Encrypted into the pool)
-        let e_add_shares = as_euint128(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: admin.clone(),
-                },
-            ),
-            add_shares as u128,
-        )?;
+#[account]
+#[derive(InitSpace)]
+pub struct CompanyProposal {
+    pub company_id: u64,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: CompanyAction,
+    pub signer_epoch: u32,
+    pub approvals: u8, // Bitmask over company.signers
+    pub approval_count: u8,
+    pub executed: bool,
+    pub bump: u8,
+}
 
-        company.shares_available = e_add(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: admin.clone(),
-                },
-            ),
-            company.shares_available,
-            e_add_shares,
-            0,
-        )?;
+/// Platform-wide settings and the platform admin key.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct PlatformConfig {
+    pub admin: Pubkey,
+    // May pause but never unpause; unpausing is admin-only
+    pub pause_guardian: Pubkey,
+    pub paused_scopes: u8, // Bitmask of PauseScope flags
+    // May freeze or cancel queued withdrawals (default = none)
+    pub compliance: Pubkey,
+    // Seconds a queued withdrawal waits before release; 0 = immediate withdrawals
+    pub withdrawal_delay: i64,
+    // Default per-vault and per-company limits per `VELOCITY_WINDOW`
+    pub velocity_limits: VelocityLimits,
+    pub bump: u8,
+}
 
-        company.active = active;
+impl PlatformConfig {
+    pub fn require_not_paused(&self, scope: PauseScope) -> Result<()> {
+        require!(
+            self.paused_scopes & (PauseScope::Global.flag() | scope.flag()) == 0,
+            DonatradeError::ProgramPaused
+        );
         Ok(())
     }
-    pub fn transfer_shares<'info>(
-        ctx: Context<'_, '_, '_, 'info, TransferShares<'info>>,
-        e_shares: Euint128,
+
+    pub fn delayed_withdrawals(&self) -> bool {
+        self.withdrawal_delay > 0
+    }
+
+    /// Limits for an account: its override if set, the platform defaults otherwise.
+    pub fn velocity_limits_for(&self, account_override: Option<VelocityLimits>) -> VelocityLimits {
+        account_override.unwrap_or(self.velocity_limits)
+    }
+}
+
+/// Caps per `VELOCITY_WINDOW`; 0 = unlimited. Purchase sizes are encrypted, so the
+/// purchase limit counts primary purchases (share buys, rights exercises, auction bids,
+/// SAFE fundings and option exercises) rather than shares or cUSD.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct VelocityLimits {
+    pub withdrawal_limit: u64, // Token base units
+    pub purchase_limit: u64,   // Primary purchases
+}
+
+/// Plaintext usage in the current window, which restarts once `VELOCITY_WINDOW` has passed.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct VelocityCounter {
+    pub window_start: i64,
+    pub used: u64,
+}
+
+impl VelocityCounter {
+    /// Count `amount` against `limit` at `now`, failing with `exceeded` if it would go over.
+    pub fn record(
+        &mut self,
+        now: i64,
+        amount: u64,
+        limit: u64,
+        exceeded: DonatradeError,
     ) -> Result<()> {
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let sender = ctx.accounts.sender.to_account_info();
+        if now >= self.window_start + VELOCITY_WINDOW {
+            self.window_start = now;
+            self.used = 0;
+        }
+        let used = self
+            .used
+            .checked_add(amount)
+            .ok_or(DonatradeError::Overflow)?;
+        if limit != 0 && used > limit {
+            return Err(exceeded.into());
+        }
+        self.used = used;
+        Ok(())
+    }
+}
 
-        // 1. Subtract from sender
-        ctx.accounts.sender_position.encrypted_shares = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: sender.clone(),
-                },
-            ),
-            ctx.accounts.sender_position.encrypted_shares,
-            e_shares,
-            0,
-        )?;
+/// A withdrawal debited from an encrypted balance and waiting out the challenge period.
+/// Tokens go to `destination` on release; compliance may freeze or cancel before then.
+#[account]
+pub struct PendingWithdrawal {
+    pub requester: Pubkey,
+    pub request_id: u64,
+    pub from_company: bool,
+    pub company_id: u64, // Source company when `from_company`
+    pub mint: Pubkey,
+    pub balance_key: Pubkey, // Vault/company key to credit back on cancel
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub covered: Ebool, // Whether the balance covered `amount`; nothing was debited otherwise
+    pub release_at: i64,
+    pub frozen: bool,
+    pub bump: u8,
+}
 
-        // 2. Add to receiver (init if needed is handled in Accounts)
-        ctx.accounts.receiver_position.owner = ctx.accounts.receiver.key();
-        ctx.accounts.receiver_position.company_id = ctx.accounts.sender_position.company_id;
-        ctx.accounts.receiver_position.bump = ctx.bumps.receiver_position;
-        ctx.accounts.receiver_position.encrypted_shares = e_add(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: sender.clone(),
-                },
-            ),
-            ctx.accounts.receiver_position.encrypted_shares,
-            e_shares,
-            0,
-        )?;
+impl Space for PendingWithdrawal {
+    const INIT_SPACE: usize = 32 + 8 + 1 + 8 + 32 + 32 + 32 + 8 + HANDLE_SPACE + 8 + 1 + 1;
+}
+
+/// Instruction categories that can be paused independently.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseScope {
+    Global,
+    Deposits,
+    Withdrawals,
+    Primary,
+    Secondary,
+}
+
+impl PauseScope {
+    pub fn flag(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct GlobalProgramVault {
+    pub usdc_token_account: Pubkey,
+    pub bump: u8,
+}
+
+/// An accepted quote currency and the `GlobalProgramVault`-owned token account holding it.
+/// Classic SPL Token and Token-2022 mints are both supported.
+#[account]
+#[derive(InitSpace)]
+pub struct QuoteMint {
+    pub mint: Pubkey,
+    pub vault_token_account: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    // Vault/company key for balances in this mint; default for the pre-registry USDC vaults
+    pub balance_key: Pubkey,
+    pub active: bool,
+    pub bump: u8,
+}
+
+/// Vault seed suffix for a balance key. USDC vaults keep their original unkeyed address.
+pub fn mint_seed(balance_key: &Pubkey) -> &[u8] {
+    if *balance_key == Pubkey::default() {
+        &[]
+    } else {
+        balance_key.as_ref()
+    }
+}
+
+#[program]
+pub mod donatrade_program {
+    use super::*;
 
+    pub fn initialize_global_vault(ctx: Context<InitializeGlobalVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.global_vault;
+        vault.usdc_token_account = ctx.accounts.usdc_token_account.key();
+        vault.bump = ctx.bumps.global_vault;
         Ok(())
     }
 
-    pub fn withdraw_company_funds<'info>(
-        ctx: Context<'_, '_, '_, 'info, WithdrawCompanyFunds<'info>>,
-        amount: u64,
+    /// Upgrade authority: create the platform config, becoming its admin and pause guardian.
+    pub fn initialize_platform_config(ctx: Context<InitializePlatformConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.platform_config;
+        config.admin = ctx.accounts.admin.key();
+        config.pause_guardian = ctx.accounts.admin.key();
+        config.bump = ctx.bumps.platform_config;
+        Ok(())
+    }
+
+    /// Platform admin: appoint the pause guardian.
+    pub fn set_pause_guardian(
+        ctx: Context<SetPauseGuardian>,
+        pause_guardian: Pubkey,
     ) -> Result<()> {
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let admin = ctx.accounts.company_admin.to_account_info();
+        ctx.accounts.platform_config.pause_guardian = pause_guardian;
+        Ok(())
+    }
 
-        // 1. Subtract from company encrypted balance
-        let e_amount = as_euint128(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: admin.clone(),
-                },
-            ),
-            amount as u128,
-        )?;
+    /// Platform admin: appoint the compliance role and set the withdrawal delay
+    /// (0 switches back to immediate withdrawals).
+    pub fn set_withdrawal_policy(
+        ctx: Context<SetWithdrawalPolicy>,
+        compliance: Pubkey,
+        withdrawal_delay: i64,
+    ) -> Result<()> {
+        require!(
+            withdrawal_delay >= 0,
+            DonatradeError::InvalidWithdrawalDelay
+        );
+        let config = &mut ctx.accounts.platform_config;
+        config.compliance = compliance;
+        config.withdrawal_delay = withdrawal_delay;
+        Ok(())
+    }
 
-        // NOT AVAILABLE in current version: e_gte.
-        // Relying on e_sub to handle/propagate encrypted overflow/underflow logic if applicable.
+    /// Platform admin: set the default velocity limits for every vault and company.
+    pub fn set_velocity_limits(
+        ctx: Context<SetVelocityLimits>,
+        limits: VelocityLimits,
+    ) -> Result<()> {
+        ctx.accounts.platform_config.velocity_limits = limits;
+        Ok(())
+    }
 
-        ctx.accounts.company_account.cusd = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: admin.clone(),
-                },
-            ),
-            ctx.accounts.company_account.cusd,
-            e_amount,
-            0,
-        )?;
+    /// Platform admin: give one vault its own velocity limits (`None` restores the defaults).
+    pub fn set_vault_velocity_override(
+        ctx: Context<SetVaultVelocityOverride>,
+        limits: Option<VelocityLimits>,
+    ) -> Result<()> {
+        ctx.accounts.investor_vault.velocity_override = limits;
+        Ok(())
+    }
 
-        // 2. Transfer physical USDC from global vault to admin
-        let seeds = &[
-            b"vault_authority".as_ref(),
-            &[ctx.accounts.global_vault.bump],
-        ];
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault_token_account.to_account_info(),
-                    to: ctx.accounts.admin_token_account.to_account_info(),
-                    authority: ctx.accounts.global_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
-            amount,
-        )?;
+    /// Platform admin: give one company its own velocity limits (`None` restores the defaults).
+    pub fn set_company_velocity_override(
+        ctx: Context<SetCompanyVelocityOverride>,
+        limits: Option<VelocityLimits>,
+    ) -> Result<()> {
+        ctx.accounts.company_account.velocity_override = limits;
+        Ok(())
+    }
 
+    /// Pause guardian or platform admin: halt an instruction category (or everything).
+    pub fn pause(ctx: Context<UpdatePause>, scope: PauseScope) -> Result<()> {
+        let config = &mut ctx.accounts.platform_config;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == config.pause_guardian || authority == config.admin,
+            DonatradeError::Unauthorized
+        );
+        config.paused_scopes |= scope.flag();
         Ok(())
     }
 
-    pub fn create_offer<'info>(
-        ctx: Context<'_, '_, '_, 'info, CreateOffer<'info>>,
-        offer_id: u64,
-        e_shares: Euint128,
+    /// Platform admin: resume an instruction category.
+    pub fn unpause(ctx: Context<UpdatePause>, scope: PauseScope) -> Result<()> {
+        let config = &mut ctx.accounts.platform_config;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            config.admin,
+            DonatradeError::Unauthorized
+        );
+        config.paused_scopes &= !scope.flag();
+        Ok(())
+    }
+
+    /// Pause guardian or platform admin: halt all activity for one company.
+    pub fn pause_company(ctx: Context<UpdateCompanyPause>) -> Result<()> {
+        let config = &ctx.accounts.platform_config;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == config.pause_guardian || authority == config.admin,
+            DonatradeError::Unauthorized
+        );
+        ctx.accounts.company_account.paused = true;
+        Ok(())
+    }
+
+    /// Platform admin: resume activity for one company.
+    pub fn unpause_company(ctx: Context<UpdateCompanyPause>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            ctx.accounts.platform_config.admin,
+            DonatradeError::Unauthorized
+        );
+        ctx.accounts.company_account.paused = false;
+        Ok(())
+    }
+
+    /// Admin-only: Activate a company that was approved off-chain.
+    /// Creates the on-chain CompanyAccount with encrypted financial state.
+    pub fn activate_company(
+        ctx: Context<ActivateCompany>,
+        company_id: u64,
+        company_admin: Pubkey,
+        initial_shares: u64,
         price_per_share: u64,
+        legal_agreement_hash: [u8; 32],
     ) -> Result<()> {
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let seller = ctx.accounts.seller.to_account_info();
+        let company = &mut ctx.accounts.company_account;
+        company.version = CURRENT_ACCOUNT_VERSION;
+        company.company_id = company_id;
+        company.company_admin = company_admin;
+        company.legal_agreement_hash = legal_agreement_hash;
+        company.legal_agreement_version = 1;
 
-        // 1. Subtract shares from seller's position (escrow them)
-        ctx.accounts.seller_position.encrypted_shares = e_sub(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: seller.clone(),
-                },
-            ),
-            ctx.accounts.seller_position.encrypted_shares,
-            e_shares,
-            0,
-        )?;
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.platform_admin.to_account_info();
 
-        // 2. Initialize the offer account
-        let offer = &mut ctx.accounts.offer_account;
-        offer.offer_id = offer_id;
-        offer.seller = ctx.accounts.seller.key();
-        offer.company_id = ctx.accounts.company_account.company_id;
-        offer.escrowed_shares = e_shares;
-        offer.price_per_share = price_per_share;
-        offer.is_active = true;
-        offer.bump = ctx.bumps.offer_account;
+        // Initialize shares available as encrypted
+        company.shares_available =
+            as_euint128(op_ctx(&inco_program, &admin), initial_shares as u128)?;
+        company.outstanding_shares = as_euint128(op_ctx(&inco_program, &admin), 0)?;
 
+        company.price_per_share = price_per_share;
+        if let Some(quote_mint) = &ctx.accounts.quote_mint {
+            company.quote_mint = quote_mint.balance_key;
+        }
+        company.active = true;
+        company.bump = ctx.bumps.company_account;
         Ok(())
     }
 
-    pub fn execute_trade<'info>(
-        ctx: Context<'_, '_, '_, 'info, ExecuteTrade<'info>>,
+    pub fn deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
+        amount: u64,
     ) -> Result<()> {
-        let offer = &mut ctx.accounts.offer_account;
-        require!(offer.is_active, DonatradeError::Inactive);
-
-        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
-        let buyer_info = ctx.accounts.buyer.to_account_info();
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Deposits)?;
+        require!(
+            ctx.accounts.quote_mint.active,
+            DonatradeError::QuoteMintInactive
+        );
 
-        // Calculate Cost (Price * Amount)
-        // Since price is plaintext and shares are encrypted, we lift price.
-        let e_price = as_euint128(
+        // 1. Transfer the quote token from investor to vault
+        token_interface::transfer_checked(
             CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: buyer_info.clone(),
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.investor_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.vault_token_account.to_account_info(),
+                    authority: ctx.accounts.investor.to_account_info(),
                 },
             ),
-            offer.price_per_share as u128,
+            amount,
+            ctx.accounts.mint.decimals,
         )?;
 
-        let e_cost = e_mul(
-            CpiContext::new(
-                inco_program.clone(),
-                Operation {
-                    signer: buyer_info.clone(),
-                },
-            ),
-            e_price,
-            offer.escrowed_shares,
-            0,
-        )?;
+        // 2. Encrypt and add to vault balance
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
 
-        // 1. Buyer pays Seller (subtract from buyer's vault)
-        ctx.accounts.buyer_vault.cusd = e_sub(
+        let e_amount = as_euint128(
             CpiContext::new(
                 inco_program.clone(),
                 Operation {
-                    signer: buyer_info.clone(),
+                    signer: investor.clone(),
                 },
             ),
-            ctx.accounts.buyer_vault.cusd,
-            e_cost,
-            0,
+            amount as u128,
         )?;
 
-        // 2. Add payment to Seller's Vault
-        ctx.accounts.seller_vault.cusd = e_add(
+        let vault = &mut ctx.accounts.investor_vault;
+        vault.version = CURRENT_ACCOUNT_VERSION;
+        vault.owner = ctx.accounts.investor.key();
+        vault.mint = ctx.accounts.quote_mint.balance_key;
+        vault.bump = ctx.bumps.investor_vault; // Store bump
+
+        // Handle first deposit: if current handle is 0, we must still use e_add
+        // to ensure the resulting handle is a valid ciphertext for Inco's storage.
+        // We cannot just assign 'e_amount' because it's a lifted plaintext.
+        if vault.cusd.0 == 0 {
+            // Create an encrypted zero
+            let e_zero = as_euint128(
+                CpiContext::new(
+                    inco_program.clone(),
+                    Operation {
+                        signer: investor.clone(),
+                    },
+                ),
+                0,
+            )?;
+
+            vault.cusd = e_add(
+                CpiContext::new(
+                    inco_program.clone(),
+                    Operation {
+                        signer: investor.clone(),
+                    },
+                ),
+                e_zero,
+                e_amount,
+                0,
+            )?;
+        } else {
+            vault.cusd = e_add(
+                CpiContext::new(
+                    inco_program.clone(),
+                    Operation {
+                        signer: investor.clone(),
+                    },
+                ),
+                vault.cusd,
+                e_amount,
+                0,
+            )?;
+        }
+
+        // 3. Let the investor decrypt the new balance right away
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(vault.cusd.0, vault.owner)?;
+
+        Ok(())
+    }
+
+    /// Company admin: publish a new legal agreement.
+    /// Bumps the version so every investor must accept it again before buying.
+    pub fn update_legal_agreement(
+        ctx: Context<UpdateLegalAgreement>,
+        legal_agreement_hash: [u8; 32],
+    ) -> Result<()> {
+        let company = &mut ctx.accounts.company_account;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::UpdateLegalAgreement {
+                legal_agreement_hash,
+            },
+        )?;
+        company.legal_agreement_hash = legal_agreement_hash;
+        company.legal_agreement_version = company
+            .legal_agreement_version
+            .checked_add(1)
+            .ok_or(DonatradeError::Overflow)?;
+        Ok(())
+    }
+
+    /// Investor: acknowledge the company's current legal agreement.
+    /// The hash and version are passed explicitly so the signature covers exactly what was read.
+    pub fn accept_legal_agreement(
+        ctx: Context<AcceptLegalAgreement>,
+        agreement_hash: [u8; 32],
+        agreement_version: u32,
+    ) -> Result<()> {
+        let company = &ctx.accounts.company_account;
+        require!(
+            agreement_hash == company.legal_agreement_hash
+                && agreement_version == company.legal_agreement_version,
+            DonatradeError::AgreementMismatch
+        );
+
+        let acceptance = &mut ctx.accounts.agreement_acceptance;
+        acceptance.investor = ctx.accounts.investor.key();
+        acceptance.company_id = company.company_id;
+        acceptance.agreement_hash = agreement_hash;
+        acceptance.agreement_version = agreement_version;
+        acceptance.accepted_at = Clock::get()?.unix_timestamp;
+        acceptance.bump = ctx.bumps.agreement_acceptance;
+        Ok(())
+    }
+
+    pub fn buy_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, BuyShares<'info>>,
+        e_shares: Euint128,
+        class_id: u8,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.investor_vault)?;
+        require!(company.active, DonatradeError::Inactive);
+        let now = Clock::get()?.unix_timestamp;
+        record_primary_purchase(
+            &ctx.accounts.platform_config,
+            &mut ctx.accounts.investor_vault,
+            company,
+            now,
+        )?;
+        require!(
+            ctx.accounts.agreement_acceptance.covers(company),
+            DonatradeError::AgreementNotAccepted
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
+
+        // 1-2. Take the shares from the class's pool and look up its encrypted price
+        let e_price = if class_id == COMMON_CLASS_ID {
+            match company.offering_mode {
+                OfferingMode::FixedPrice => {}
+                OfferingMode::Dutch => require!(
+                    now >= company.dutch_schedule.starts_at,
+                    DonatradeError::AuctionNotOpen
+                ),
+                OfferingMode::SealedBid => return err!(DonatradeError::WrongOfferingMode),
+            }
+            require!(
+                !company.rights_period.is_open(now),
+                DonatradeError::RightsPeriodActive
+            );
+
+            // Subtract shares from available pool (Encrypted check happens in e_sub)
+            company.shares_available = e_sub(
+                CpiContext::new(
+                    inco_program.clone(),
+                    Operation {
+                        signer: investor.clone(),
+                    },
+                ),
+                company.shares_available,
+                e_shares,
+                0,
+            )?;
+            track_outstanding(
+                &inco_program,
+                &investor,
+                &mut company.outstanding_shares,
+                e_shares,
+                true,
+            )?;
+
+            // Stored handle in private mode, lifted plaintext otherwise
+            price_handle(
+                &inco_program,
+                &investor,
+                company.private_pricing,
+                company.encrypted_price,
+                company.current_price(now),
+            )?
+        } else {
+            let class = share_class_for(company, class_id, ctx.accounts.share_class.as_mut())?;
+            require!(class.active, DonatradeError::Inactive);
+            class.shares_available = e_sub(
+                op_ctx(&inco_program, &investor),
+                class.shares_available,
+                e_shares,
+                0,
+            )?;
+            track_outstanding(
+                &inco_program,
+                &investor,
+                &mut class.outstanding_shares,
+                e_shares,
+                true,
+            )?;
+            as_euint128(
+                op_ctx(&inco_program, &investor),
+                class.price_per_share as u128,
+            )?
+        };
+
+        // 3. Calculate Cost (Price * Amount) via e_mul
+        let e_cost = e_mul(
             CpiContext::new(
                 inco_program.clone(),
                 Operation {
-                    signer: buyer_info.clone(),
+                    signer: investor.clone(),
                 },
             ),
-            ctx.accounts.seller_vault.cusd,
+            e_price,
+            e_shares,
+            0,
+        )?;
+
+        // 4. Subtract Cost from Investor Vault
+        ctx.accounts.investor_vault.cusd = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            ctx.accounts.investor_vault.cusd,
             e_cost,
             0,
         )?;
 
-        // 3. Transfer escrowed shares to buyer's position
-        // Initialize buyer's position if new
-        ctx.accounts.buyer_position.owner = ctx.accounts.buyer.key();
-        ctx.accounts.buyer_position.company_id = ctx.accounts.company_account.company_id;
-        ctx.accounts.buyer_position.bump = ctx.bumps.buyer_position;
+        // 5. Add Cost to Company Balance
+        company.cusd = e_add(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            company.cusd,
+            e_cost,
+            0,
+        )?;
 
-        // Add escrowed shares to buyer's position
-        ctx.accounts.buyer_position.encrypted_shares = e_add(
+        // 6. Update Position
+        ctx.accounts.position.version = CURRENT_ACCOUNT_VERSION;
+        ctx.accounts.position.owner = ctx.accounts.investor.key();
+        ctx.accounts.position.company_id = company.company_id;
+        ctx.accounts.position.class_id = class_id;
+        ctx.accounts.position.bump = ctx.bumps.position;
+        ctx.accounts.position.encrypted_shares = e_add(
             CpiContext::new(
                 inco_program.clone(),
                 Operation {
-                    signer: buyer_info.clone(),
+                    signer: investor.clone(),
                 },
             ),
-            ctx.accounts.buyer_position.encrypted_shares,
-            offer.escrowed_shares,
+            ctx.accounts.position.encrypted_shares,
+            e_shares,
+            0,
+        )?;
+
+        // 7. Decryption grants: investor for position and vault, company admin and auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let investor_key = ctx.accounts.investor.key();
+        grants.allow(ctx.accounts.position.encrypted_shares.0, investor_key)?;
+        grants.allow(ctx.accounts.investor_vault.cusd.0, investor_key)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, ctx.accounts.position.encrypted_shares.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+
+        Ok(())
+    }
+
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Withdrawals)?;
+        require!(
+            !ctx.accounts.platform_config.delayed_withdrawals(),
+            DonatradeError::WithdrawalDelayRequired
+        );
+        require_keys_eq!(
+            ctx.accounts.investor_vault.mint,
+            ctx.accounts.quote_mint.balance_key,
+            DonatradeError::QuoteMintMismatch
+        );
+        let limits = ctx
+            .accounts
+            .platform_config
+            .velocity_limits_for(ctx.accounts.investor_vault.velocity_override);
+        ctx.accounts.investor_vault.withdrawals.record(
+            Clock::get()?.unix_timestamp,
+            amount,
+            limits.withdrawal_limit,
+            DonatradeError::WithdrawalLimitExceeded,
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
+
+        // 1. Subtract from encrypted balance
+        let e_amount = as_euint128(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            amount as u128,
+        )?;
+        ctx.accounts.investor_vault.cusd = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            ctx.accounts.investor_vault.cusd,
+            e_amount,
+            0,
+        )?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.investor_vault.cusd.0,
+            ctx.accounts.investor.key(),
+        )?;
+
+        // 2. Transfer physical tokens from vault to investor
+        let seeds = &[
+            b"vault_authority".as_ref(),
+            &[ctx.accounts.global_vault.bump],
+        ];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.investor_token_account.to_account_info(),
+                    authority: ctx.accounts.global_vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        Ok(())
+    }
+
+    /// Vault owner: let `allowed_address` decrypt the vault's current cUSD balance.
+    pub fn share_vault_balance(ctx: Context<ShareVaultBalance>) -> Result<()> {
+        let handle = ctx.accounts.investor_vault.cusd.0;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.owner,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            handle,
+            true,
+        )
+    }
+
+    /// Vault owner: withdraw a previously shared view of the current balance.
+    pub fn revoke_vault_balance(ctx: Context<ShareVaultBalance>) -> Result<()> {
+        let handle = ctx.accounts.investor_vault.cusd.0;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.owner,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            handle,
+            false,
+        )
+    }
+
+    /// Position owner: let `allowed_address` decrypt the position's current share count.
+    pub fn share_position(ctx: Context<SharePosition>) -> Result<()> {
+        let handle = ctx.accounts.position.encrypted_shares.0;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.owner,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            handle,
+            true,
+        )
+    }
+
+    /// Position owner: withdraw a previously shared view of the current share count.
+    pub fn revoke_position(ctx: Context<SharePosition>) -> Result<()> {
+        let handle = ctx.accounts.position.encrypted_shares.0;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.owner,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            handle,
+            false,
+        )
+    }
+
+    /// Company admin: let `allowed_address` decrypt the company's current cUSD balance.
+    pub fn share_company_balance(ctx: Context<ShareCompanyBalance>) -> Result<()> {
+        let handle = ctx.accounts.company_account.cusd.0;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.company_admin,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            handle,
+            true,
+        )
+    }
+
+    /// Company admin: withdraw a previously shared view of the company balance.
+    pub fn revoke_company_balance(ctx: Context<ShareCompanyBalance>) -> Result<()> {
+        let handle = ctx.accounts.company_account.cusd.0;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.company_admin,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            handle,
+            false,
+        )
+    }
+
+    pub fn sell_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, SellShares<'info>>,
+        e_shares: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.investor_vault)?;
+        // Don't check plaintext overflow on price.
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
+
+        // 2. Value = shares * price (lift price to encrypted unless already private)
+        let e_price = price_handle(
+            &inco_program,
+            &investor,
+            company.private_pricing,
+            company.encrypted_price,
+            company.current_price(Clock::get()?.unix_timestamp),
+        )?;
+
+        let e_val = e_mul(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            e_shares,
+            e_price,
+            0, // scalar_byte
+        )?;
+
+        // Update Position and Company warehouse
+        ctx.accounts.position.encrypted_shares = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            ctx.accounts.position.encrypted_shares,
+            e_shares,
             0,
         )?;
 
-        // 4. Clear escrowed shares and deactivate offer
-        offer.escrowed_shares = Euint128(0);
-        offer.is_active = false;
+        company.cusd = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            company.cusd,
+            e_val,
+            0,
+        )?;
+
+        // Add shares back to company (Private pool)
+        company.shares_available = e_add(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: investor.clone(),
+                },
+            ),
+            company.shares_available,
+            e_shares,
+            0,
+        )?;
+        track_outstanding(
+            &inco_program,
+            &investor,
+            &mut company.outstanding_shares,
+            e_shares,
+            false,
+        )?;
+
+        // Decryption grants: investor for position, company admin and auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.position.encrypted_shares.0,
+            ctx.accounts.investor.key(),
+        )?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, ctx.accounts.position.encrypted_shares.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    pub fn update_offering(
+        ctx: Context<UpdateOffering>,
+        new_price: u64,
+        add_shares: u64,
+        active: bool,
+        rights_period: i64,
+        outstanding_shares: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::UpdateOffering {
+                new_price,
+                add_shares,
+                active,
+                rights_period,
+                outstanding_shares,
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        // The Dutch schedule sets the price while it runs
+        require!(
+            company.offering_mode != OfferingMode::Dutch || new_price == 0,
+            DonatradeError::WrongOfferingMode
+        );
+
+        // Update price (plaintext). In private mode a zero price keeps the encrypted
+        // price; a non-zero price switches the company back to public pricing.
+        if company.offering_mode != OfferingMode::Dutch
+            && (!company.private_pricing || new_price != 0)
+        {
+            company.private_pricing = false;
+            company.encrypted_price = Euint128::default();
+            company.price_per_share = new_price;
+        }
+
+        // Add shares (Encrypted into the pool)
+        let e_add_shares = as_euint128(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: admin.clone(),
+                },
+            ),
+            add_shares as u128,
+        )?;
+
+        company.shares_available = e_add(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: admin.clone(),
+                },
+            ),
+            company.shares_available,
+            e_add_shares,
+            0,
+        )?;
+
+        // Optionally reserve the new supply for existing holders, pro rata
+        if rights_period > 0 {
+            let now = Clock::get()?.unix_timestamp;
+            require!(
+                add_shares > 0
+                    && outstanding_shares > 0
+                    && company.offering_mode != OfferingMode::SealedBid
+                    && !company.rights_period.is_open(now),
+                DonatradeError::InvalidRightsPeriod
+            );
+            company.rights_period = RightsPeriod {
+                round: company.rights_period.round + 1,
+                new_shares: add_shares,
+                outstanding_shares,
+                ends_at: now + rights_period,
+            };
+        }
+
+        company.active = active;
+        Ok(())
+    }
+
+    /// Company admin: switch to private pricing with a client-encrypted price.
+    /// The plaintext price is cleared; the admin and auditor may decrypt the new price.
+    pub fn set_private_price<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPrivatePrice<'info>>,
+        e_price: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::SetPrivatePrice { e_price: e_price.0 },
+        )?;
+
+        company.private_pricing = true;
+        company.encrypted_price = e_price;
+        company.price_per_share = 0;
+
+        let mut grants = HandleGrants::new(
+            ctx.accounts.inco_lightning_program.to_account_info(),
+            ctx.accounts.company_admin.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(company.encrypted_price.0, company.company_admin)?;
+        grants.allow_auditor(company, company.encrypted_price.0)?;
+        Ok(())
+    }
+
+    /// Company admin: let an investor decrypt the private price.
+    pub fn share_private_price(ctx: Context<ShareCompanyBalance>) -> Result<()> {
+        let company = &ctx.accounts.company_account;
+        require!(
+            company.private_pricing,
+            DonatradeError::PrivatePricingDisabled
+        );
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.company_admin,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            company.encrypted_price.0,
+            true,
+        )
+    }
+
+    /// Company admin: withdraw an investor's view of the private price.
+    pub fn revoke_private_price(ctx: Context<ShareCompanyBalance>) -> Result<()> {
+        let company = &ctx.accounts.company_account;
+        require!(
+            company.private_pricing,
+            DonatradeError::PrivatePricingDisabled
+        );
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.company_admin,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            company.encrypted_price.0,
+            false,
+        )
+    }
+
+    /// Seller: let a prospective buyer decrypt a private offer's price.
+    pub fn share_offer_price(ctx: Context<ShareOfferPrice>) -> Result<()> {
+        let offer = &ctx.accounts.offer_account;
+        require!(
+            offer.private_pricing,
+            DonatradeError::PrivatePricingDisabled
+        );
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.seller,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            offer.encrypted_price.0,
+            true,
+        )
+    }
+
+    /// Company signer: propose a sensitive action. The proposer's approval is counted.
+    pub fn propose_company_action(
+        ctx: Context<ProposeCompanyAction>,
+        proposal_id: u64,
+        action: CompanyAction,
+    ) -> Result<()> {
+        let company = &ctx.accounts.company_account;
+        require!(
+            company.multisig_enabled(),
+            DonatradeError::MultisigNotEnabled
+        );
+        let index = company
+            .signer_index(&ctx.accounts.proposer.key())
+            .ok_or(DonatradeError::NotCompanySigner)?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.company_id = company.company_id;
+        proposal.proposal_id = proposal_id;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.action = action;
+        proposal.signer_epoch = company.signer_epoch;
+        proposal.approvals = 1 << index;
+        proposal.approval_count = 1;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        Ok(())
+    }
+
+    /// Company signer: approve an open proposal.
+    pub fn approve_company_action(ctx: Context<ApproveCompanyAction>) -> Result<()> {
+        let company = &ctx.accounts.company_account;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, DonatradeError::ProposalExecuted);
+        require!(
+            proposal.signer_epoch == company.signer_epoch,
+            DonatradeError::StaleProposal
+        );
+        let index = company
+            .signer_index(&ctx.accounts.approver.key())
+            .ok_or(DonatradeError::NotCompanySigner)?;
+        require!(
+            proposal.approvals & (1 << index) == 0,
+            DonatradeError::AlreadyApproved
+        );
+
+        proposal.approvals |= 1 << index;
+        proposal.approval_count += 1;
+        Ok(())
+    }
+
+    /// Replace the company multisig signer set.
+    /// Without a multisig the company admin sets it directly; afterwards it needs an approved proposal.
+    pub fn set_company_signers(
+        ctx: Context<SetCompanySigners>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let action = CompanyAction::set_signers(&signers, threshold)?;
+        let company = &mut ctx.accounts.company_account;
+        let authority = ctx.accounts.authority.key();
+        if company.multisig_enabled() {
+            require!(
+                company.signer_index(&authority).is_some(),
+                DonatradeError::NotCompanySigner
+            );
+        } else {
+            require_keys_eq!(
+                authority,
+                company.company_admin,
+                DonatradeError::Unauthorized
+            );
+        }
+        authorize_company_action(company, ctx.accounts.proposal.as_mut(), &action)?;
+
+        if let CompanyAction::SetSigners {
+            signers,
+            signer_count,
+            threshold,
+        } = action
+        {
+            company.signers = signers;
+            company.signer_count = signer_count;
+            company.threshold = threshold;
+        }
+        company.signer_epoch = company
+            .signer_epoch
+            .checked_add(1)
+            .ok_or(DonatradeError::Overflow)?;
+        Ok(())
+    }
+
+    /// Hand the company admin role to a new wallet.
+    /// Signed by the current admin, or by any company signer with an approved proposal when multisig is on.
+    pub fn rotate_company_admin(ctx: Context<RotateCompanyAdmin>, new_admin: Pubkey) -> Result<()> {
+        let company = &mut ctx.accounts.company_account;
+        let authority = ctx.accounts.authority.key();
+        if company.multisig_enabled() {
+            require!(
+                company.signer_index(&authority).is_some(),
+                DonatradeError::NotCompanySigner
+            );
+        } else {
+            require_keys_eq!(
+                authority,
+                company.company_admin,
+                DonatradeError::Unauthorized
+            );
+        }
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::RotateAdmin { new_admin },
+        )?;
+
+        company.company_admin = new_admin;
+        Ok(())
+    }
+
+    /// Platform admin: recover a company whose founder wallet was lost.
+    /// Once the company has a multisig, a threshold of its signers must approve the new admin.
+    pub fn recover_company_admin(
+        ctx: Context<RecoverCompanyAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        let company = &mut ctx.accounts.company_account;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::RecoverAdmin { new_admin },
+        )?;
+        company.company_admin = new_admin;
+        Ok(())
+    }
+
+    /// Company admin with platform admin co-signature: appoint (or clear, with the default key) the auditor.
+    /// From then on every new position and company balance handle is also allowed for the auditor.
+    pub fn set_company_auditor(ctx: Context<SetCompanyAuditor>, auditor: Pubkey) -> Result<()> {
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::SetAuditor { auditor },
+        )?;
+        ctx.accounts.company_account.auditor = auditor;
+        Ok(())
+    }
+
+    pub fn transfer_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferShares<'info>>,
+        e_shares: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        ctx.accounts.company_account.require_not_paused()?;
+        require_transferable(
+            &ctx.accounts.company_account,
+            ctx.accounts.sender_position.class_id,
+            ctx.accounts.share_class.as_ref(),
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let sender = ctx.accounts.sender.to_account_info();
+
+        // 1. Subtract from sender
+        ctx.accounts.sender_position.encrypted_shares = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: sender.clone(),
+                },
+            ),
+            ctx.accounts.sender_position.encrypted_shares,
+            e_shares,
+            0,
+        )?;
+
+        // 2. Add to receiver (init if needed is handled in Accounts)
+        ctx.accounts.receiver_position.version = CURRENT_ACCOUNT_VERSION;
+        ctx.accounts.receiver_position.owner = ctx.accounts.receiver.key();
+        ctx.accounts.receiver_position.company_id = ctx.accounts.sender_position.company_id;
+        ctx.accounts.receiver_position.class_id = ctx.accounts.sender_position.class_id;
+        ctx.accounts.receiver_position.bump = ctx.bumps.receiver_position;
+        ctx.accounts.receiver_position.encrypted_shares = e_add(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: sender.clone(),
+                },
+            ),
+            ctx.accounts.receiver_position.encrypted_shares,
+            e_shares,
+            0,
+        )?;
+
+        // 3. Decryption grants: each owner for their position, then the auditor for both
+        let mut grants = HandleGrants::new(
+            inco_program,
+            sender,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.sender_position.encrypted_shares.0,
+            ctx.accounts.sender.key(),
+        )?;
+        grants.allow(
+            ctx.accounts.receiver_position.encrypted_shares.0,
+            ctx.accounts.receiver.key(),
+        )?;
+        let company = &ctx.accounts.company_account;
+        grants.allow_auditor(company, ctx.accounts.sender_position.encrypted_shares.0)?;
+        grants.allow_auditor(company, ctx.accounts.receiver_position.encrypted_shares.0)?;
+
+        Ok(())
+    }
+
+    /// Sender: move encrypted cUSD to another investor's vault, creating it if needed.
+    /// An amount above the sender's balance moves nothing.
+    pub fn transfer_cusd<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferCusd<'info>>,
+        e_amount: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        require_keys_neq!(
+            ctx.accounts.sender.key(),
+            ctx.accounts.receiver.key(),
+            DonatradeError::SelfTransfer
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let sender = ctx.accounts.sender.to_account_info();
+
+        // 1. Encrypted balance guard: move the amount only if the sender can cover it
+        let e_zero = as_euint128(op_ctx(&inco_program, &sender), 0)?;
+        let fits = e_ge(
+            op_ctx(&inco_program, &sender),
+            ctx.accounts.sender_vault.cusd,
+            e_amount,
+            0,
+        )?;
+        let e_moved = e_select(op_ctx(&inco_program, &sender), fits, e_amount, e_zero, 0)?;
+
+        // 2. Subtract from sender
+        ctx.accounts.sender_vault.cusd = e_sub(
+            op_ctx(&inco_program, &sender),
+            ctx.accounts.sender_vault.cusd,
+            e_moved,
+            0,
+        )?;
+
+        // 3. Add to receiver (init if needed is handled in Accounts)
+        let receiver_vault = &mut ctx.accounts.receiver_vault;
+        if receiver_vault.version == 0 {
+            receiver_vault.version = CURRENT_ACCOUNT_VERSION;
+            receiver_vault.owner = ctx.accounts.receiver.key();
+            receiver_vault.mint = ctx.accounts.sender_vault.mint;
+            receiver_vault.bump = ctx.bumps.receiver_vault;
+        }
+        let e_balance = if receiver_vault.cusd.0 == 0 {
+            e_zero
+        } else {
+            receiver_vault.cusd
+        };
+        receiver_vault.cusd = e_add(op_ctx(&inco_program, &sender), e_balance, e_moved, 0)?;
+
+        // 4. Decryption grants: each owner for their vault
+        let mut grants = HandleGrants::new(
+            inco_program,
+            sender,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.sender_vault.cusd.0, ctx.accounts.sender.key())?;
+        grants.allow(receiver_vault.cusd.0, receiver_vault.owner)?;
+        Ok(())
+    }
+
+    pub fn withdraw_company_funds<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawCompanyFunds<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Withdrawals)?;
+        require!(
+            !ctx.accounts.platform_config.delayed_withdrawals(),
+            DonatradeError::WithdrawalDelayRequired
+        );
+        ctx.accounts.company_account.require_not_paused()?;
+        require_keys_eq!(
+            ctx.accounts.company_account.quote_mint,
+            ctx.accounts.quote_mint.balance_key,
+            DonatradeError::QuoteMintMismatch
+        );
+        let limits = ctx
+            .accounts
+            .platform_config
+            .velocity_limits_for(ctx.accounts.company_account.velocity_override);
+        ctx.accounts.company_account.withdrawals.record(
+            Clock::get()?.unix_timestamp,
+            amount,
+            limits.withdrawal_limit,
+            DonatradeError::WithdrawalLimitExceeded,
+        )?;
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::WithdrawFunds {
+                amount,
+                destination: ctx.accounts.admin_token_account.key(),
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        // 1. Subtract from company encrypted balance
+        let e_amount = as_euint128(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: admin.clone(),
+                },
+            ),
+            amount as u128,
+        )?;
+
+        // NOT AVAILABLE in current version: e_gte.
+        // Relying on e_sub to handle/propagate encrypted overflow/underflow logic if applicable.
+
+        ctx.accounts.company_account.cusd = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: admin.clone(),
+                },
+            ),
+            ctx.accounts.company_account.cusd,
+            e_amount,
+            0,
+        )?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let company = &ctx.accounts.company_account;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+
+        // 2. Transfer physical tokens from global vault to admin
+        let seeds = &[
+            b"vault_authority".as_ref(),
+            &[ctx.accounts.global_vault.bump],
+        ];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.admin_token_account.to_account_info(),
+                    authority: ctx.accounts.global_vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn create_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateOffer<'info>>,
+        offer_id: u64,
+        e_shares: Euint128,
+        price_per_share: u64,
+        e_price: Option<Euint128>,
+        counterparty: Option<Pubkey>,
+        allowlist: Vec<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        ctx.accounts.company_account.require_not_paused()?;
+        // Offers in a private-pricing company must not reveal a plaintext price
+        require!(
+            !ctx.accounts.company_account.private_pricing
+                || (e_price.is_some() && price_per_share == 0),
+            DonatradeError::PrivatePriceRequired
+        );
+        require!(
+            allowlist.len() <= MAX_OFFER_ALLOWLIST
+                && counterparty != Some(Pubkey::default())
+                && !allowlist.contains(&Pubkey::default()),
+            DonatradeError::InvalidAllowlist
+        );
+        require_transferable(
+            &ctx.accounts.company_account,
+            ctx.accounts.seller_position.class_id,
+            ctx.accounts.share_class.as_ref(),
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let seller = ctx.accounts.seller.to_account_info();
+
+        // 1. Subtract shares from seller's position (escrow them)
+        ctx.accounts.seller_position.encrypted_shares = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: seller.clone(),
+                },
+            ),
+            ctx.accounts.seller_position.encrypted_shares,
+            e_shares,
+            0,
+        )?;
+
+        // 2. Initialize the offer account
+        let offer = &mut ctx.accounts.offer_account;
+        offer.version = CURRENT_ACCOUNT_VERSION;
+        offer.offer_id = offer_id;
+        offer.seller = ctx.accounts.seller.key();
+        offer.company_id = ctx.accounts.company_account.company_id;
+        offer.class_id = ctx.accounts.seller_position.class_id;
+        offer.escrowed_shares = e_shares;
+        offer.price_per_share = price_per_share;
+        offer.private_pricing = e_price.is_some();
+        offer.encrypted_price = e_price.unwrap_or_default();
+        offer.counterparty = counterparty.unwrap_or_default();
+        offer.allowlist[..allowlist.len()].copy_from_slice(&allowlist);
+        offer.allowlist_count = allowlist.len() as u8;
+        offer.is_active = true;
+        offer.bump = ctx.bumps.offer_account;
+
+        // 3. Decryption grants: seller for the position (and private price), then auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            seller,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.seller_position.encrypted_shares.0,
+            ctx.accounts.seller.key(),
+        )?;
+        if offer.private_pricing {
+            grants.allow(offer.encrypted_price.0, offer.seller)?;
+        }
+        grants.allow_auditor(
+            &ctx.accounts.company_account,
+            ctx.accounts.seller_position.encrypted_shares.0,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn execute_trade<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteTrade<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        ctx.accounts.company_account.require_not_paused()?;
+        require_quote_mint(&ctx.accounts.company_account, &ctx.accounts.buyer_vault)?;
+        require_quote_mint(&ctx.accounts.company_account, &ctx.accounts.seller_vault)?;
+        let offer = &mut ctx.accounts.offer_account;
+        require!(offer.is_active, DonatradeError::Inactive);
+        require!(
+            offer.may_buy(&ctx.accounts.buyer.key()),
+            DonatradeError::NotOfferCounterparty
+        );
+        require_transferable(
+            &ctx.accounts.company_account,
+            offer.class_id,
+            ctx.accounts.share_class.as_ref(),
+        )?;
+        require!(
+            ctx.accounts
+                .agreement_acceptance
+                .covers(&ctx.accounts.company_account),
+            DonatradeError::AgreementNotAccepted
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let buyer_info = ctx.accounts.buyer.to_account_info();
+
+        // Calculate Cost (Price * Amount)
+        // Shares are encrypted; a plaintext price is lifted, a private one used as is.
+        let e_price = price_handle(
+            &inco_program,
+            &buyer_info,
+            offer.private_pricing,
+            offer.encrypted_price,
+            offer.price_per_share,
+        )?;
+
+        let e_cost = e_mul(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: buyer_info.clone(),
+                },
+            ),
+            e_price,
+            offer.escrowed_shares,
+            0,
+        )?;
+
+        // 1. Buyer pays Seller (subtract from buyer's vault)
+        ctx.accounts.buyer_vault.cusd = e_sub(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: buyer_info.clone(),
+                },
+            ),
+            ctx.accounts.buyer_vault.cusd,
+            e_cost,
+            0,
+        )?;
+
+        // 2. Add payment to Seller's Vault
+        ctx.accounts.seller_vault.cusd = e_add(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: buyer_info.clone(),
+                },
+            ),
+            ctx.accounts.seller_vault.cusd,
+            e_cost,
+            0,
+        )?;
+
+        // 3. Transfer escrowed shares to buyer's position
+        // Initialize buyer's position if new
+        ctx.accounts.buyer_position.version = CURRENT_ACCOUNT_VERSION;
+        ctx.accounts.buyer_position.owner = ctx.accounts.buyer.key();
+        ctx.accounts.buyer_position.company_id = ctx.accounts.company_account.company_id;
+        ctx.accounts.buyer_position.class_id = offer.class_id;
+        ctx.accounts.buyer_position.bump = ctx.bumps.buyer_position;
+
+        // Add escrowed shares to buyer's position
+        ctx.accounts.buyer_position.encrypted_shares = e_add(
+            CpiContext::new(
+                inco_program.clone(),
+                Operation {
+                    signer: buyer_info.clone(),
+                },
+            ),
+            ctx.accounts.buyer_position.encrypted_shares,
+            offer.escrowed_shares,
+            0,
+        )?;
+
+        // 4. Clear escrowed shares and deactivate offer
+        offer.escrowed_shares = Euint128(0);
+        offer.is_active = false;
+
+        // 5. Decryption grants: buyer and seller for their vaults, buyer and auditor for the position
+        let mut grants = HandleGrants::new(
+            inco_program,
+            buyer_info,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let buyer_key = ctx.accounts.buyer.key();
+        grants.allow(ctx.accounts.buyer_vault.cusd.0, buyer_key)?;
+        grants.allow(
+            ctx.accounts.seller_vault.cusd.0,
+            ctx.accounts.seller_vault.owner,
+        )?;
+        grants.allow(ctx.accounts.buyer_position.encrypted_shares.0, buyer_key)?;
+        grants.allow_auditor(
+            &ctx.accounts.company_account,
+            ctx.accounts.buyer_position.encrypted_shares.0,
+        )?;
+
+        Ok(())
+    }
+
+    /// Permissionless: upgrade a v1 or v2 InvestorVault. The payer covers any extra rent.
+    pub fn migrate_investor_vault(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<InvestorVault>(
+            &ctx.accounts.account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            legacy::INVESTOR_VAULT_LAYOUTS,
+            8 + InvestorVault::INIT_SPACE,
+        )
+    }
+
+    /// Permissionless: upgrade a v1 or v2 CompanyAccount. The payer covers any extra rent.
+    pub fn migrate_company_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<CompanyAccount>(
+            &ctx.accounts.account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            legacy::COMPANY_ACCOUNT_LAYOUTS,
+            8 + CompanyAccount::INIT_SPACE,
+        )
+    }
+
+    /// Permissionless: upgrade a v1 or v2 PositionAccount. The payer covers any extra rent.
+    pub fn migrate_position(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<PositionAccount>(
+            &ctx.accounts.account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            legacy::POSITION_ACCOUNT_LAYOUTS,
+            8 + PositionAccount::INIT_SPACE,
+        )
+    }
+
+    /// Permissionless: upgrade a v1 or v2 OfferAccount. The payer covers any extra rent.
+    pub fn migrate_offer(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<OfferAccount>(
+            &ctx.accounts.account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            legacy::OFFER_ACCOUNT_LAYOUTS,
+            8 + OfferAccount::INIT_SPACE,
+        )
+    }
+
+    /// Owner: start closing a position by computing an encrypted `shares == 0` check.
+    /// The owner is allowed to decrypt the check and submits the attested result to `close_position`.
+    pub fn request_close_position(ctx: Context<RequestClosePosition>) -> Result<()> {
+        let balance = ctx.accounts.position.encrypted_shares;
+        let zero_check = encrypted_is_zero(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.owner,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.system_program,
+            balance,
+        )?;
+
+        let request = &mut ctx.accounts.close_request;
+        request.owner = ctx.accounts.owner.key();
+        request.target = ctx.accounts.position.key();
+        request.balance_handle = balance.0;
+        request.zero_check = zero_check;
+        request.bump = ctx.bumps.close_request;
+        Ok(())
+    }
+
+    /// Owner: close a position proven empty and reclaim its rent.
+    /// Requires an Inco attestation, in the same transaction, that the zero check decrypts to true.
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let request = &ctx.accounts.close_request;
+        require!(
+            request.balance_handle == ctx.accounts.position.encrypted_shares.0,
+            DonatradeError::StaleCloseRequest
+        );
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.owner,
+            &[(request.zero_check.0, 1)],
+        )
+    }
+
+    /// Owner: start closing a vault by computing an encrypted `cusd == 0` check.
+    pub fn request_close_vault(ctx: Context<RequestCloseVault>) -> Result<()> {
+        let balance = ctx.accounts.investor_vault.cusd;
+        let zero_check = encrypted_is_zero(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.owner,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.system_program,
+            balance,
+        )?;
+
+        let request = &mut ctx.accounts.close_request;
+        request.owner = ctx.accounts.owner.key();
+        request.target = ctx.accounts.investor_vault.key();
+        request.balance_handle = balance.0;
+        request.zero_check = zero_check;
+        request.bump = ctx.bumps.close_request;
+        Ok(())
+    }
+
+    /// Owner: close a vault proven empty and reclaim its rent.
+    /// A vault still holding cUSD can never pass the check, so no funds are destroyed.
+    pub fn close_vault(ctx: Context<CloseVault>) -> Result<()> {
+        let request = &ctx.accounts.close_request;
+        require!(
+            request.balance_handle == ctx.accounts.investor_vault.cusd.0,
+            DonatradeError::StaleCloseRequest
+        );
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.owner,
+            &[(request.zero_check.0, 1)],
+        )
+    }
+
+    /// Company admin: open a sealed-bid auction for `supply` shares carved out of the pool.
+    /// `price_levels` is the ascending ladder the clearing price is chosen from.
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        auction_id: u64,
+        supply: u64,
+        price_levels: Vec<u64>,
+        closes_at: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require!(
+            company.offering_mode == OfferingMode::FixedPrice,
+            DonatradeError::WrongOfferingMode
+        );
+        require!(
+            supply > 0
+                && !price_levels.is_empty()
+                && price_levels.len() <= MAX_AUCTION_PRICE_LEVELS
+                && price_levels[0] > 0
+                && price_levels.windows(2).all(|pair| pair[0] < pair[1]),
+            DonatradeError::InvalidAuctionParams
+        );
+        require!(
+            closes_at > Clock::get()?.unix_timestamp,
+            DonatradeError::InvalidAuctionParams
+        );
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::CreateAuction {
+                auction_id,
+                supply,
+                price_levels: price_levels.clone(),
+                closes_at,
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        // Move the auctioned supply out of the fixed-price pool
+        let e_supply = as_euint128(op_ctx(&inco_program, &admin), supply as u128)?;
+        company.shares_available = e_sub(
+            op_ctx(&inco_program, &admin),
+            company.shares_available,
+            e_supply,
+            0,
+        )?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &admin), 0)?;
+
+        let auction = &mut ctx.accounts.auction;
+        auction.company_id = company.company_id;
+        auction.auction_id = auction_id;
+        auction.supply = supply;
+        auction.level_count = price_levels.len() as u8;
+        auction.price_levels[..price_levels.len()].copy_from_slice(&price_levels);
+        auction.demand = [e_zero; MAX_AUCTION_PRICE_LEVELS];
+        auction.remaining = e_supply;
+        auction.closes_at = closes_at;
+        auction.clearing_price = 0;
+        auction.bid_count = 0;
+        auction.settled_count = 0;
+        auction.status = AuctionStatus::Open;
+        auction.bump = ctx.bumps.auction;
+        auction.clearing_level = 0;
+        auction.marginal_supply = 0;
+        auction.marginal_demand = 0;
+
+        company.offering_mode = OfferingMode::SealedBid;
+        Ok(())
+    }
+
+    /// Investor: place an encrypted bid. `quantity * max_price` cUSD is escrowed from the vault
+    /// and the bid is folded into the encrypted demand curve. A bid the vault cannot cover, or
+    /// for more than the supply, is recorded as empty.
+    pub fn submit_bid<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitBid<'info>>,
+        e_quantity: Euint128,
+        e_max_price: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require!(company.active, DonatradeError::Inactive);
+        require_quote_mint(company, &ctx.accounts.investor_vault)?;
+        require!(
+            ctx.accounts.agreement_acceptance.covers(company),
+            DonatradeError::AgreementNotAccepted
+        );
+        let auction = &mut ctx.accounts.auction;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            auction.status == AuctionStatus::Open && now < auction.closes_at,
+            DonatradeError::AuctionNotOpen
+        );
+        record_primary_purchase(
+            &ctx.accounts.platform_config,
+            &mut ctx.accounts.investor_vault,
+            company,
+            now,
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
+
+        // 1. Bound the bid so quantity * max price cannot wrap: quantity within the supply,
+        //    price within u64 (a zero price reaches no level)
+        let e_zero = as_euint128(op_ctx(&inco_program, &investor), 0)?;
+        let e_supply = as_euint128(op_ctx(&inco_program, &investor), auction.supply as u128)?;
+        let in_supply = e_ge(op_ctx(&inco_program, &investor), e_supply, e_quantity, 0)?;
+        let e_quantity = e_select(
+            op_ctx(&inco_program, &investor),
+            in_supply,
+            e_quantity,
+            e_zero,
+            0,
+        )?;
+        let e_price_cap = as_euint128(op_ctx(&inco_program, &investor), u64::MAX as u128)?;
+        let in_range = e_ge(
+            op_ctx(&inco_program, &investor),
+            e_price_cap,
+            e_max_price,
+            0,
+        )?;
+        let e_max_price = e_select(
+            op_ctx(&inco_program, &investor),
+            in_range,
+            e_max_price,
+            e_zero,
+            0,
+        )?;
+
+        // 2. Escrow quantity * max price out of the vault; a bid the vault cannot cover
+        //    escrows nothing and adds no demand
+        let e_wanted = e_mul(op_ctx(&inco_program, &investor), e_quantity, e_max_price, 0)?;
+        let covered = e_ge(
+            op_ctx(&inco_program, &investor),
+            ctx.accounts.investor_vault.cusd,
+            e_wanted,
+            0,
+        )?;
+        let e_escrow = e_select(
+            op_ctx(&inco_program, &investor),
+            covered,
+            e_wanted,
+            e_zero,
+            0,
+        )?;
+        let e_quantity = e_select(
+            op_ctx(&inco_program, &investor),
+            covered,
+            e_quantity,
+            e_zero,
+            0,
+        )?;
+        ctx.accounts.investor_vault.cusd = e_sub(
+            op_ctx(&inco_program, &investor),
+            ctx.accounts.investor_vault.cusd,
+            e_escrow,
+            0,
+        )?;
+
+        // 3. Add the quantity to demand at every level the bid reaches
+        for level in 0..auction.level_count as usize {
+            let e_level = as_euint128(
+                op_ctx(&inco_program, &investor),
+                auction.price_levels[level] as u128,
+            )?;
+            let reaches = e_ge(op_ctx(&inco_program, &investor), e_max_price, e_level, 0)?;
+            let e_demand = e_select(
+                op_ctx(&inco_program, &investor),
+                reaches,
+                e_quantity,
+                e_zero,
+                0,
+            )?;
+            auction.demand[level] = e_add(
+                op_ctx(&inco_program, &investor),
+                auction.demand[level],
+                e_demand,
+                0,
+            )?;
+        }
+
+        // 4. Record the bid
+        let bid = &mut ctx.accounts.bid;
+        bid.bidder = ctx.accounts.investor.key();
+        bid.company_id = auction.company_id;
+        bid.auction_id = auction.auction_id;
+        bid.sequence = auction.bid_count;
+        bid.quantity = e_quantity;
+        bid.max_price = e_max_price;
+        bid.escrow = e_escrow;
+        bid.bump = ctx.bumps.bid;
+        auction.bid_count += 1;
+
+        // Make sure the position exists for settlement
+        let position = &mut ctx.accounts.position;
+        if position.version == 0 {
+            position.version = CURRENT_ACCOUNT_VERSION;
+            position.owner = ctx.accounts.investor.key();
+            position.company_id = auction.company_id;
+            position.bump = ctx.bumps.position;
+        }
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.investor_vault.cusd.0, bid.bidder)?;
+        Ok(())
+    }
+
+    /// Permissionless after the deadline: close bidding and let the company admin decrypt the
+    /// demand curve to finalize the clearing price.
+    pub fn close_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseAuction<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let auction = &mut ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Open
+                && Clock::get()?.unix_timestamp >= auction.closes_at,
+            DonatradeError::AuctionNotClosable
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let payer = ctx.accounts.payer.to_account_info();
+
+        // No bids: the whole supply goes straight back to the pool
+        if auction.bid_count == 0 {
+            let company = &mut ctx.accounts.company_account;
+            company.shares_available = e_add(
+                op_ctx(&inco_program, &payer),
+                company.shares_available,
+                auction.remaining,
+                0,
+            )?;
+            company.offering_mode = OfferingMode::FixedPrice;
+            auction.status = AuctionStatus::Settled;
+            return Ok(());
+        }
+
+        let mut grants = HandleGrants::new(
+            inco_program.clone(),
+            payer.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        for level in 0..auction.level_count as usize {
+            grants.allow(
+                auction.demand[level].0,
+                ctx.accounts.company_account.company_admin,
+            )?;
+        }
+        auction.status = AuctionStatus::Closed;
+        Ok(())
+    }
+
+    /// Company admin: fix the clearing price at `level` with attested decryptions of the
+    /// cumulative demand there and one level up. Demand must meet supply at `level` (unless
+    /// it is the floor) and fall short one level up; see `Auction::marginal_split`.
+    pub fn finalize_auction_price(
+        ctx: Context<FinalizeAuctionPrice>,
+        level: u8,
+        demand_at: u64,
+        demand_above: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let auction = &mut ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Closed,
+            DonatradeError::AuctionNotClosable
+        );
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::FinalizeAuctionPrice {
+                auction_id: auction.auction_id,
+                level,
+            },
+        )?;
+        let level = level as usize;
+        let (marginal_supply, marginal_demand) =
+            auction.marginal_split(level, demand_at, demand_above)?;
+
+        let mut decryptions = vec![(auction.demand[level].0, demand_at as u128)];
+        if level + 1 < auction.level_count as usize {
+            decryptions.push((auction.demand[level + 1].0, demand_above as u128));
+        }
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.company_admin,
+            &decryptions,
+        )?;
+
+        auction.clearing_price = auction.price_levels[level];
+        auction.clearing_level = level as u8;
+        auction.marginal_supply = marginal_supply;
+        auction.marginal_demand = marginal_demand;
+        auction.status = AuctionStatus::Priced;
+        Ok(())
+    }
+
+    /// Permissionless crank: settle one bid, in any order. Bids above the clearing level fill in
+    /// full and bids at it are prorated over the marginal supply, all at the clearing price;
+    /// the rest of each escrow is refunded. Losing bids are refunded in full without revealing
+    /// anything.
+    pub fn settle_bid<'info>(ctx: Context<'_, '_, '_, 'info, SettleBid<'info>>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        ctx.accounts.company_account.require_not_paused()?;
+        let auction = &mut ctx.accounts.auction;
+        let bid = &ctx.accounts.bid;
+        require!(
+            auction.status == AuctionStatus::Priced,
+            DonatradeError::AuctionNotPriced
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let payer = ctx.accounts.payer.to_account_info();
+        let company = &mut ctx.accounts.company_account;
+
+        // 1. Allocation: bids above the clearing level fill in full, bids at it share the
+        //    marginal supply pro-rata, bids below it get nothing
+        let e_zero = as_euint128(op_ctx(&inco_program, &payer), 0)?;
+        let e_marginal_fill = if auction.marginal_supply >= auction.marginal_demand {
+            bid.quantity
+        } else {
+            e_mul_div(
+                &inco_program,
+                &payer,
+                bid.quantity,
+                auction.marginal_supply,
+                auction.marginal_demand,
+            )?
+        };
+        let above = auction.clearing_level as usize + 1;
+        let e_fill = if above < auction.level_count as usize {
+            let e_above = as_euint128(
+                op_ctx(&inco_program, &payer),
+                auction.price_levels[above] as u128,
+            )?;
+            let outbids = e_ge(op_ctx(&inco_program, &payer), bid.max_price, e_above, 0)?;
+            e_select(
+                op_ctx(&inco_program, &payer),
+                outbids,
+                bid.quantity,
+                e_marginal_fill,
+                0,
+            )?
+        } else {
+            e_marginal_fill
+        };
+        let e_clearing = as_euint128(
+            op_ctx(&inco_program, &payer),
+            auction.clearing_price as u128,
+        )?;
+        let wins = e_ge(op_ctx(&inco_program, &payer), bid.max_price, e_clearing, 0)?;
+        let e_alloc = e_select(op_ctx(&inco_program, &payer), wins, e_fill, e_zero, 0)?;
+        auction.remaining = e_sub(op_ctx(&inco_program, &payer), auction.remaining, e_alloc, 0)?;
+
+        // 2. Pay the clearing price, refund the rest of the escrow
+        let e_cost = e_mul(op_ctx(&inco_program, &payer), e_alloc, e_clearing, 0)?;
+        let e_refund = e_sub(op_ctx(&inco_program, &payer), bid.escrow, e_cost, 0)?;
+        company.cusd = e_add(op_ctx(&inco_program, &payer), company.cusd, e_cost, 0)?;
+
+        // A vault or position closed while the bid was open is recreated empty
+        let vault = &mut ctx.accounts.bidder_vault;
+        if vault.version == 0 {
+            vault.version = CURRENT_ACCOUNT_VERSION;
+            vault.owner = bid.bidder;
+            vault.mint = company.quote_mint;
+            vault.bump = ctx.bumps.bidder_vault;
+            vault.cusd = e_zero;
+        }
+        vault.cusd = e_add(op_ctx(&inco_program, &payer), vault.cusd, e_refund, 0)?;
+        let position = &mut ctx.accounts.bidder_position;
+        if position.version == 0 {
+            position.version = CURRENT_ACCOUNT_VERSION;
+            position.owner = bid.bidder;
+            position.company_id = company.company_id;
+            position.bump = ctx.bumps.bidder_position;
+            position.encrypted_shares = e_zero;
+        }
+        position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &payer),
+            position.encrypted_shares,
+            e_alloc,
+            0,
+        )?;
+        track_outstanding(
+            &inco_program,
+            &payer,
+            &mut company.outstanding_shares,
+            e_alloc,
+            true,
+        )?;
+
+        // 3. After the last bid, unsold supply returns to the pool
+        auction.settled_count += 1;
+        if auction.settled_count == auction.bid_count {
+            company.shares_available = e_add(
+                op_ctx(&inco_program, &payer),
+                company.shares_available,
+                auction.remaining,
+                0,
+            )?;
+            auction.status = AuctionStatus::Settled;
+            company.offering_mode = OfferingMode::FixedPrice;
+        }
+
+        // 4. Decryption grants: bidder for vault and position, company admin and auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            payer,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.bidder_vault.cusd.0, bid.bidder)?;
+        grants.allow(ctx.accounts.bidder_position.encrypted_shares.0, bid.bidder)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, ctx.accounts.bidder_position.encrypted_shares.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Company admin: switch to a Dutch offering whose price falls linearly from
+    /// `start_price` to `floor_price` between `starts_at` and `ends_at`.
+    pub fn start_dutch_auction(
+        ctx: Context<DutchAuctionAdmin>,
+        start_price: u64,
+        floor_price: u64,
+        starts_at: i64,
+        ends_at: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require!(
+            company.offering_mode == OfferingMode::FixedPrice,
+            DonatradeError::WrongOfferingMode
+        );
+        // A public descending price would leak a private valuation
+        require!(!company.private_pricing, DonatradeError::WrongOfferingMode);
+        require!(
+            start_price >= floor_price && floor_price > 0 && starts_at < ends_at,
+            DonatradeError::InvalidAuctionParams
+        );
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::StartDutchAuction {
+                start_price,
+                floor_price,
+                starts_at,
+                ends_at,
+            },
+        )?;
+
+        company.dutch_schedule = DutchSchedule {
+            start_price,
+            floor_price,
+            starts_at,
+            ends_at,
+            ..Default::default()
+        };
+        company.offering_mode = OfferingMode::Dutch;
+        Ok(())
+    }
+
+    /// Company admin: compute an encrypted `shares_available == 0` check so the auction
+    /// can be ended before its window closes.
+    pub fn request_dutch_exhaustion_check(ctx: Context<RequestDutchExhaustionCheck>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        require!(
+            company.offering_mode == OfferingMode::Dutch,
+            DonatradeError::WrongOfferingMode
+        );
+        let shares_available = company.shares_available;
+        company.dutch_schedule.exhausted_check = encrypted_is_zero(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.company_admin,
+            &ctx.accounts.allowance_account,
+            &ctx.accounts.system_program,
+            shares_available,
+        )?;
+        company.dutch_schedule.exhausted_check_of = shares_available.0;
+        Ok(())
+    }
+
+    /// Company admin: end the Dutch offering. After the window this is unconditional;
+    /// before it, requires an attested decryption showing the shares are exhausted.
+    /// The company returns to fixed pricing at the final Dutch price.
+    pub fn end_dutch_auction(ctx: Context<EndDutchAuction>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        require!(
+            company.offering_mode == OfferingMode::Dutch,
+            DonatradeError::WrongOfferingMode
+        );
+        let now = Clock::get()?.unix_timestamp;
+        if now < company.dutch_schedule.ends_at {
+            require!(
+                company.dutch_schedule.exhausted_check_of == company.shares_available.0,
+                DonatradeError::StaleExhaustionCheck
+            );
+            verify_decryption(
+                &ctx.accounts.inco_lightning_program,
+                &ctx.accounts.instructions,
+                &ctx.accounts.company_admin,
+                &[(company.dutch_schedule.exhausted_check.0, 1)],
+            )?;
+        }
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::EndDutchAuction,
+        )?;
+
+        company.price_per_share = company.dutch_schedule.price_at(now);
+        company.dutch_schedule = DutchSchedule::default();
+        company.offering_mode = OfferingMode::FixedPrice;
+        Ok(())
+    }
+
+    /// Company admin: attach a freshly allocated order book account to the company.
+    pub fn initialize_order_book(ctx: Context<InitializeOrderBook>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        let company = &mut ctx.accounts.company_account;
+        require_keys_eq!(
+            company.order_book,
+            Pubkey::default(),
+            DonatradeError::OrderBookMismatch
+        );
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::InitializeOrderBook {
+                order_book: ctx.accounts.order_book.key(),
+            },
+        )?;
+
+        let mut book = ctx.accounts.order_book.load_init()?;
+        book.company_id = company.company_id;
+
+        company.order_book = ctx.accounts.order_book.key();
+        Ok(())
+    }
+
+    /// Trader: rest a limit order on the company's book.
+    /// Bids escrow `quantity * price` cUSD from the vault; asks escrow the shares from the position.
+    /// An order the balance cannot cover rests with a zero quantity.
+    pub fn place_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceOrder<'info>>,
+        side: OrderSide,
+        price: u64,
+        e_quantity: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        let company = &ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.trader_vault)?;
+        require!(price > 0, DonatradeError::InvalidOrder);
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let trader = ctx.accounts.trader.to_account_info();
+        let trader_key = ctx.accounts.trader.key();
+
+        // 1. Escrow cUSD for a bid, shares for an ask; an order the balance cannot cover
+        //    rests with a zero quantity
+        let e_zero = as_euint128(op_ctx(&inco_program, &trader), 0)?;
+        let e_quantity = match side {
+            OrderSide::Bid => {
+                let accepted = ctx
+                    .accounts
+                    .agreement_acceptance
+                    .as_ref()
+                    .is_some_and(|acceptance| acceptance.covers(company));
+                require!(accepted, DonatradeError::AgreementNotAccepted);
+                // Quantity within u64 so quantity * price cannot wrap
+                let e_cap = as_euint128(op_ctx(&inco_program, &trader), u64::MAX as u128)?;
+                let in_range = e_ge(op_ctx(&inco_program, &trader), e_cap, e_quantity, 0)?;
+                let e_quantity = e_select(
+                    op_ctx(&inco_program, &trader),
+                    in_range,
+                    e_quantity,
+                    e_zero,
+                    0,
+                )?;
+                let e_price = as_euint128(op_ctx(&inco_program, &trader), price as u128)?;
+                let e_wanted = e_mul(op_ctx(&inco_program, &trader), e_quantity, e_price, 0)?;
+                let covered = e_ge(
+                    op_ctx(&inco_program, &trader),
+                    ctx.accounts.trader_vault.cusd,
+                    e_wanted,
+                    0,
+                )?;
+                let e_escrow =
+                    e_select(op_ctx(&inco_program, &trader), covered, e_wanted, e_zero, 0)?;
+                ctx.accounts.trader_vault.cusd = e_sub(
+                    op_ctx(&inco_program, &trader),
+                    ctx.accounts.trader_vault.cusd,
+                    e_escrow,
+                    0,
+                )?;
+                e_select(
+                    op_ctx(&inco_program, &trader),
+                    covered,
+                    e_quantity,
+                    e_zero,
+                    0,
+                )?
+            }
+            OrderSide::Ask => {
+                let covered = e_ge(
+                    op_ctx(&inco_program, &trader),
+                    ctx.accounts.position.encrypted_shares,
+                    e_quantity,
+                    0,
+                )?;
+                let e_quantity = e_select(
+                    op_ctx(&inco_program, &trader),
+                    covered,
+                    e_quantity,
+                    e_zero,
+                    0,
+                )?;
+                ctx.accounts.position.encrypted_shares = e_sub(
+                    op_ctx(&inco_program, &trader),
+                    ctx.accounts.position.encrypted_shares,
+                    e_quantity,
+                    0,
+                )?;
+                e_quantity
+            }
+        };
+
+        // Make sure the position exists to receive fills
+        let position = &mut ctx.accounts.position;
+        if position.version == 0 {
+            position.version = CURRENT_ACCOUNT_VERSION;
+            position.owner = trader_key;
+            position.company_id = company.company_id;
+            position.bump = ctx.bumps.position;
+        }
+
+        // 2. Rest the order
+        ctx.accounts
+            .order_book
+            .load_mut()?
+            .insert_order(side, trader_key, price, e_quantity.0)?;
+
+        // 3. Decryption grants: trader for the resting quantity and the debited balance,
+        //    auditor for a debited position
+        let mut grants = HandleGrants::new(
+            inco_program,
+            trader,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(e_quantity.0, trader_key)?;
+        match side {
+            OrderSide::Bid => grants.allow(ctx.accounts.trader_vault.cusd.0, trader_key)?,
+            OrderSide::Ask => {
+                grants.allow(ctx.accounts.position.encrypted_shares.0, trader_key)?;
+                grants.allow_auditor(company, ctx.accounts.position.encrypted_shares.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Order owner: pull a resting order and get back what is left of its escrow.
+    /// Allowed while paused so traders can always leave the book.
+    pub fn cancel_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelOrder<'info>>,
+        slot: u16,
+    ) -> Result<()> {
+        let order = ctx.accounts.order_book.load()?.order(slot)?;
+        require_keys_eq!(
+            order.owner,
+            ctx.accounts.owner.key(),
+            DonatradeError::Unauthorized
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let owner = ctx.accounts.owner.to_account_info();
+        let owner_key = ctx.accounts.owner.key();
+        let e_remaining = Euint128(order.remaining);
+        require_quote_mint(&ctx.accounts.company_account, &ctx.accounts.owner_vault)?;
+
+        let mut grants = HandleGrants::new(
+            inco_program.clone(),
+            owner.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        match order.side() {
+            OrderSide::Bid => {
+                let e_price = as_euint128(op_ctx(&inco_program, &owner), order.price as u128)?;
+                let e_refund = e_mul(op_ctx(&inco_program, &owner), e_remaining, e_price, 0)?;
+                ctx.accounts.owner_vault.cusd = e_add(
+                    op_ctx(&inco_program, &owner),
+                    ctx.accounts.owner_vault.cusd,
+                    e_refund,
+                    0,
+                )?;
+                grants.allow(ctx.accounts.owner_vault.cusd.0, owner_key)?;
+            }
+            OrderSide::Ask => {
+                ctx.accounts.position.encrypted_shares = e_add(
+                    op_ctx(&inco_program, &owner),
+                    ctx.accounts.position.encrypted_shares,
+                    e_remaining,
+                    0,
+                )?;
+                grants.allow(ctx.accounts.position.encrypted_shares.0, owner_key)?;
+                grants.allow_auditor(
+                    &ctx.accounts.company_account,
+                    ctx.accounts.position.encrypted_shares.0,
+                )?;
+            }
+        }
+
+        ctx.accounts.order_book.load_mut()?.remove_order(slot)
+    }
+
+    /// Permissionless crank: evict the head order on `side` when its owner closed an account
+    /// `match_orders` needs (vault or position for a bid, vault for an ask), so it would block the book.
+    /// The missing accounts are recreated at the cranker's expense and the escrow refunded into them.
+    pub fn evict_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, EvictOrder<'info>>,
+        side: OrderSide,
+    ) -> Result<()> {
+        let (slot, order) = {
+            let book = ctx.accounts.order_book.load()?;
+            let slot = book.best(side).ok_or(DonatradeError::OrderNotFound)?;
+            (slot, book.order(slot)?)
+        };
+        require_keys_eq!(
+            order.owner,
+            ctx.accounts.owner.key(),
+            DonatradeError::OrderBookMismatch
+        );
+
+        // 1. Only orders whose settlement accounts were gone (and just recreated) qualify
+        let vault_missing = ctx.accounts.owner_vault.version == 0;
+        let position_missing = ctx.accounts.position.version == 0;
+        let unsettleable = match side {
+            OrderSide::Bid => vault_missing || position_missing,
+            OrderSide::Ask => vault_missing,
+        };
+        require!(unsettleable, DonatradeError::OrderSettleable);
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let cranker = ctx.accounts.cranker.to_account_info();
+        let company = &ctx.accounts.company_account;
+        let e_zero = as_euint128(op_ctx(&inco_program, &cranker), 0)?;
+        let vault = &mut ctx.accounts.owner_vault;
+        if vault_missing {
+            vault.version = CURRENT_ACCOUNT_VERSION;
+            vault.owner = order.owner;
+            vault.mint = company.quote_mint;
+            vault.bump = ctx.bumps.owner_vault;
+            vault.cusd = e_zero;
+        }
+        let position = &mut ctx.accounts.position;
+        if position_missing {
+            position.version = CURRENT_ACCOUNT_VERSION;
+            position.owner = order.owner;
+            position.company_id = company.company_id;
+            position.bump = ctx.bumps.position;
+            position.encrypted_shares = e_zero;
+        }
+
+        // 2. Refund what is left of the escrow, as cancel_order would
+        let e_remaining = Euint128(order.remaining);
+        let mut grants = HandleGrants::new(
+            inco_program.clone(),
+            cranker.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        match side {
+            OrderSide::Bid => {
+                let e_price = as_euint128(op_ctx(&inco_program, &cranker), order.price as u128)?;
+                let e_refund = e_mul(op_ctx(&inco_program, &cranker), e_remaining, e_price, 0)?;
+                vault.cusd = e_add(op_ctx(&inco_program, &cranker), vault.cusd, e_refund, 0)?;
+            }
+            OrderSide::Ask => {
+                position.encrypted_shares = e_add(
+                    op_ctx(&inco_program, &cranker),
+                    position.encrypted_shares,
+                    e_remaining,
+                    0,
+                )?;
+            }
+        }
+        grants.allow(vault.cusd.0, order.owner)?;
+        grants.allow(position.encrypted_shares.0, order.owner)?;
+        grants.allow_auditor(company, position.encrypted_shares.0)?;
+
+        ctx.accounts.order_book.load_mut()?.remove_order(slot)
+    }
+
+    /// Permissionless crank: match the oldest orders at the best bid and ask if they cross.
+    /// Trades at the maker's (earlier order's) price for `min(bid, ask)` encrypted shares;
+    /// a buyer whose limit was above the trade price is refunded the difference.
+    /// Fresh `remaining == 0` checks are granted to the cranker for `pop_filled_order`.
+    pub fn match_orders<'info>(ctx: Context<'_, '_, '_, 'info, MatchOrders<'info>>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        ctx.accounts.company_account.require_not_paused()?;
+        require_quote_mint(&ctx.accounts.company_account, &ctx.accounts.buyer_vault)?;
+        require_quote_mint(&ctx.accounts.company_account, &ctx.accounts.seller_vault)?;
+        require_transferable(&ctx.accounts.company_account, COMMON_CLASS_ID, None)?;
+
+        let (bid_slot, bid, ask_slot, ask) = {
+            let book = ctx.accounts.order_book.load()?;
+            let bid_slot = book
+                .best(OrderSide::Bid)
+                .ok_or(DonatradeError::NoCrossingOrders)?;
+            let ask_slot = book
+                .best(OrderSide::Ask)
+                .ok_or(DonatradeError::NoCrossingOrders)?;
+            (
+                bid_slot,
+                book.order(bid_slot)?,
+                ask_slot,
+                book.order(ask_slot)?,
+            )
+        };
+        require!(bid.price >= ask.price, DonatradeError::NoCrossingOrders);
+        require_keys_eq!(
+            ctx.accounts.buyer_vault.owner,
+            bid.owner,
+            DonatradeError::OrderBookMismatch
+        );
+        require_keys_eq!(
+            ctx.accounts.buyer_position.owner,
+            bid.owner,
+            DonatradeError::OrderBookMismatch
+        );
+        require_keys_eq!(
+            ctx.accounts.seller_vault.owner,
+            ask.owner,
+            DonatradeError::OrderBookMismatch
+        );
+        let trade_price = if bid.sequence < ask.sequence {
+            bid.price
+        } else {
+            ask.price
+        };
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let cranker = ctx.accounts.cranker.to_account_info();
+
+        // 1. Fill = min(bid remaining, ask remaining)
+        let bid_remaining = Euint128(bid.remaining);
+        let ask_remaining = Euint128(ask.remaining);
+        let bid_larger = e_ge(
+            op_ctx(&inco_program, &cranker),
+            bid_remaining,
+            ask_remaining,
+            0,
+        )?;
+        let e_fill = e_select(
+            op_ctx(&inco_program, &cranker),
+            bid_larger,
+            ask_remaining,
+            bid_remaining,
+            0,
+        )?;
+
+        // 2. Seller is paid from the bid escrow; buyer gets the shares and any price improvement
+        let e_price = as_euint128(op_ctx(&inco_program, &cranker), trade_price as u128)?;
+        let e_cost = e_mul(op_ctx(&inco_program, &cranker), e_fill, e_price, 0)?;
+        ctx.accounts.seller_vault.cusd = e_add(
+            op_ctx(&inco_program, &cranker),
+            ctx.accounts.seller_vault.cusd,
+            e_cost,
+            0,
+        )?;
+        if bid.price > trade_price {
+            let e_improvement = as_euint128(
+                op_ctx(&inco_program, &cranker),
+                (bid.price - trade_price) as u128,
+            )?;
+            let e_refund = e_mul(op_ctx(&inco_program, &cranker), e_fill, e_improvement, 0)?;
+            ctx.accounts.buyer_vault.cusd = e_add(
+                op_ctx(&inco_program, &cranker),
+                ctx.accounts.buyer_vault.cusd,
+                e_refund,
+                0,
+            )?;
+        }
+        ctx.accounts.buyer_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &cranker),
+            ctx.accounts.buyer_position.encrypted_shares,
+            e_fill,
+            0,
+        )?;
+
+        // 3. Shrink both orders and refresh their empty checks
+        let e_zero = as_euint128(op_ctx(&inco_program, &cranker), 0)?;
+        let bid_remaining = e_sub(op_ctx(&inco_program, &cranker), bid_remaining, e_fill, 0)?;
+        let ask_remaining = e_sub(op_ctx(&inco_program, &cranker), ask_remaining, e_fill, 0)?;
+        let bid_empty = e_eq(op_ctx(&inco_program, &cranker), bid_remaining, e_zero, 0)?;
+        let ask_empty = e_eq(op_ctx(&inco_program, &cranker), ask_remaining, e_zero, 0)?;
+        {
+            let mut book = ctx.accounts.order_book.load_mut()?;
+            book.orders[bid_slot as usize].remaining = bid_remaining.0;
+            book.orders[bid_slot as usize].empty_check = bid_empty.0;
+            book.orders[ask_slot as usize].remaining = ask_remaining.0;
+            book.orders[ask_slot as usize].empty_check = ask_empty.0;
+        }
+
+        // 4. Decryption grants: owners for balances and orders, cranker for the checks, then auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            cranker,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.buyer_vault.cusd.0, bid.owner)?;
+        grants.allow(ctx.accounts.buyer_position.encrypted_shares.0, bid.owner)?;
+        grants.allow(bid_remaining.0, bid.owner)?;
+        grants.allow(ctx.accounts.seller_vault.cusd.0, ask.owner)?;
+        grants.allow(ask_remaining.0, ask.owner)?;
+        grants.allow(bid_empty.0, ctx.accounts.cranker.key())?;
+        grants.allow(ask_empty.0, ctx.accounts.cranker.key())?;
+        grants.allow_auditor(
+            &ctx.accounts.company_account,
+            ctx.accounts.buyer_position.encrypted_shares.0,
+        )?;
+        Ok(())
+    }
+
+    /// Cranker: remove the head order on `side` once an attested decryption shows it is fully filled.
+    pub fn pop_filled_order(ctx: Context<PopFilledOrder>, side: OrderSide) -> Result<()> {
+        let mut book = ctx.accounts.order_book.load_mut()?;
+        let slot = book.best(side).ok_or(DonatradeError::OrderNotFound)?;
+        let order = book.order(slot)?;
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.cranker,
+            &[(order.empty_check, 1)],
+        )?;
+        book.remove_order(slot)
+    }
+
+    /// Maker: escrow `e_give` shares of one company and ask for `e_want` shares of another.
+    pub fn create_swap_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateSwapOffer<'info>>,
+        swap_id: u64,
+        e_give: Euint128,
+        e_want: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        let give_company = &ctx.accounts.give_company;
+        let want_company = &ctx.accounts.want_company;
+        give_company.require_not_paused()?;
+        want_company.require_not_paused()?;
+        require!(
+            give_company.company_id != want_company.company_id,
+            DonatradeError::InvalidSwap
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let maker = ctx.accounts.maker.to_account_info();
+        let maker_key = ctx.accounts.maker.key();
+
+        // 1. Escrow the maker's shares
+        ctx.accounts.maker_give_position.encrypted_shares = e_sub(
+            op_ctx(&inco_program, &maker),
+            ctx.accounts.maker_give_position.encrypted_shares,
+            e_give,
+            0,
+        )?;
+
+        // 2. Record the swap
+        let swap = &mut ctx.accounts.swap_offer;
+        swap.maker = maker_key;
+        swap.swap_id = swap_id;
+        swap.give_company_id = give_company.company_id;
+        swap.escrowed_shares = e_give;
+        swap.want_company_id = want_company.company_id;
+        swap.want_shares = e_want;
+        swap.bump = ctx.bumps.swap_offer;
+
+        // 3. Decryption grants: maker for the position and both legs, then auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            maker,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.maker_give_position.encrypted_shares.0,
+            maker_key,
+        )?;
+        grants.allow(swap.escrowed_shares.0, maker_key)?;
+        grants.allow(swap.want_shares.0, maker_key)?;
+        grants.allow_auditor(
+            give_company,
+            ctx.accounts.maker_give_position.encrypted_shares.0,
+        )?;
+        Ok(())
+    }
+
+    /// Maker: let a prospective taker decrypt both legs of a swap.
+    pub fn share_swap_terms(ctx: Context<ShareSwapTerms>) -> Result<()> {
+        let swap = &ctx.accounts.swap_offer;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.maker,
+            &ctx.accounts.give_allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            swap.escrowed_shares.0,
+            true,
+        )?;
+        set_allowance(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.maker,
+            &ctx.accounts.want_allowance_account,
+            &ctx.accounts.allowed_address,
+            &ctx.accounts.system_program,
+            swap.want_shares.0,
+            true,
+        )
+    }
+
+    /// Taker: complete a swap. The requested shares move from the taker to the maker and
+    /// the escrowed shares to the taker, atomically; the swap account is closed to the maker.
+    /// If the taker holds fewer shares than requested, neither leg moves and the escrow is returned.
+    pub fn take_swap_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, TakeSwapOffer<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        let give_company = &ctx.accounts.give_company;
+        let want_company = &ctx.accounts.want_company;
+        give_company.require_not_paused()?;
+        want_company.require_not_paused()?;
+        require_transferable(give_company, COMMON_CLASS_ID, None)?;
+        require_transferable(want_company, COMMON_CLASS_ID, None)?;
+        // Each side acquires shares of a company and must have accepted its agreement
+        require!(
+            ctx.accounts.taker_acceptance.covers(give_company)
+                && ctx.accounts.maker_acceptance.covers(want_company),
+            DonatradeError::AgreementNotAccepted
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let taker = ctx.accounts.taker.to_account_info();
+        let taker_key = ctx.accounts.taker.key();
+        let maker_key = ctx.accounts.maker.key();
+        let swap = &ctx.accounts.swap_offer;
+
+        // Initialize new positions
+        let maker_want_position = &mut ctx.accounts.maker_want_position;
+        if maker_want_position.version == 0 {
+            maker_want_position.version = CURRENT_ACCOUNT_VERSION;
+            maker_want_position.owner = maker_key;
+            maker_want_position.company_id = want_company.company_id;
+            maker_want_position.bump = ctx.bumps.maker_want_position;
+        }
+        let taker_give_position = &mut ctx.accounts.taker_give_position;
+        if taker_give_position.version == 0 {
+            taker_give_position.version = CURRENT_ACCOUNT_VERSION;
+            taker_give_position.owner = taker_key;
+            taker_give_position.company_id = give_company.company_id;
+            taker_give_position.bump = ctx.bumps.taker_give_position;
+        }
+        let maker_give_position = &mut ctx.accounts.maker_give_position;
+        if maker_give_position.version == 0 {
+            maker_give_position.version = CURRENT_ACCOUNT_VERSION;
+            maker_give_position.owner = maker_key;
+            maker_give_position.company_id = give_company.company_id;
+            maker_give_position.bump = ctx.bumps.maker_give_position;
+        }
+
+        // 1. Both legs move only if the taker holds the wanted shares; otherwise the escrow goes back
+        let e_zero = as_euint128(op_ctx(&inco_program, &taker), 0)?;
+        let fits = e_ge(
+            op_ctx(&inco_program, &taker),
+            ctx.accounts.taker_want_position.encrypted_shares,
+            swap.want_shares,
+            0,
+        )?;
+        let e_want = e_select(
+            op_ctx(&inco_program, &taker),
+            fits,
+            swap.want_shares,
+            e_zero,
+            0,
+        )?;
+        let e_give = e_select(
+            op_ctx(&inco_program, &taker),
+            fits,
+            swap.escrowed_shares,
+            e_zero,
+            0,
+        )?;
+        let e_returned = e_select(
+            op_ctx(&inco_program, &taker),
+            fits,
+            e_zero,
+            swap.escrowed_shares,
+            0,
+        )?;
+
+        // 2. Want leg: taker -> maker
+        ctx.accounts.taker_want_position.encrypted_shares = e_sub(
+            op_ctx(&inco_program, &taker),
+            ctx.accounts.taker_want_position.encrypted_shares,
+            e_want,
+            0,
+        )?;
+        maker_want_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &taker),
+            maker_want_position.encrypted_shares,
+            e_want,
+            0,
+        )?;
+
+        // 3. Give leg: escrow -> taker, or back to the maker
+        taker_give_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &taker),
+            taker_give_position.encrypted_shares,
+            e_give,
+            0,
+        )?;
+        maker_give_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &taker),
+            maker_give_position.encrypted_shares,
+            e_returned,
+            0,
+        )?;
+
+        // 4. Decryption grants: each owner for their positions, then each company's auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            taker,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.taker_want_position.encrypted_shares.0,
+            taker_key,
+        )?;
+        grants.allow(maker_want_position.encrypted_shares.0, maker_key)?;
+        grants.allow(taker_give_position.encrypted_shares.0, taker_key)?;
+        grants.allow(maker_give_position.encrypted_shares.0, maker_key)?;
+        grants.allow_auditor(
+            want_company,
+            ctx.accounts.taker_want_position.encrypted_shares.0,
+        )?;
+        grants.allow_auditor(want_company, maker_want_position.encrypted_shares.0)?;
+        grants.allow_auditor(give_company, taker_give_position.encrypted_shares.0)?;
+        grants.allow_auditor(give_company, maker_give_position.encrypted_shares.0)?;
+        Ok(())
+    }
+
+    /// Maker: withdraw a swap offer and return the escrowed shares.
+    pub fn cancel_swap_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelSwapOffer<'info>>,
+    ) -> Result<()> {
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let maker = ctx.accounts.maker.to_account_info();
+
+        ctx.accounts.maker_give_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &maker),
+            ctx.accounts.maker_give_position.encrypted_shares,
+            ctx.accounts.swap_offer.escrowed_shares,
+            0,
+        )?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            maker,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.maker_give_position.encrypted_shares.0,
+            ctx.accounts.maker.key(),
+        )?;
+        grants.allow_auditor(
+            &ctx.accounts.give_company,
+            ctx.accounts.maker_give_position.encrypted_shares.0,
+        )?;
+        Ok(())
+    }
+
+    /// Company admin: open a SAFE round with a valuation cap and/or a discount in basis points.
+    pub fn create_safe_round(
+        ctx: Context<CreateSafeRound>,
+        round_id: u64,
+        valuation_cap: u64,
+        discount_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        require!(discount_bps < 10_000, DonatradeError::InvalidSafeTerms);
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::CreateSafeRound {
+                round_id,
+                valuation_cap,
+                discount_bps,
+            },
+        )?;
+
+        let round = &mut ctx.accounts.safe_round;
+        round.company_id = ctx.accounts.company_account.company_id;
+        round.round_id = round_id;
+        round.valuation_cap = valuation_cap;
+        round.discount_bps = discount_bps;
+        round.conversion_price = 0;
+        round.bump = ctx.bumps.safe_round;
+        Ok(())
+    }
+
+    /// Investor: fund a SAFE with encrypted cUSD from the vault. The money goes to the company now;
+    /// shares are issued at conversion.
+    pub fn fund_safe<'info>(
+        ctx: Context<'_, '_, '_, 'info, FundSafe<'info>>,
+        e_amount: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.investor_vault)?;
+        require!(
+            !ctx.accounts.safe_round.is_priced(),
+            DonatradeError::SafeRoundPriced
+        );
+        require!(
+            ctx.accounts.agreement_acceptance.covers(company),
+            DonatradeError::AgreementNotAccepted
+        );
+        record_primary_purchase(
+            &ctx.accounts.platform_config,
+            &mut ctx.accounts.investor_vault,
+            company,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
+        let investor_key = ctx.accounts.investor.key();
+
+        // 1. Move cUSD from the vault to the company
+        ctx.accounts.investor_vault.cusd = e_sub(
+            op_ctx(&inco_program, &investor),
+            ctx.accounts.investor_vault.cusd,
+            e_amount,
+            0,
+        )?;
+        company.cusd = e_add(op_ctx(&inco_program, &investor), company.cusd, e_amount, 0)?;
+
+        // 2. Add it to the SAFE's principal
+        let safe = &mut ctx.accounts.safe;
+        if safe.investor == Pubkey::default() {
+            safe.investor = investor_key;
+            safe.company_id = company.company_id;
+            safe.round_id = ctx.accounts.safe_round.round_id;
+            safe.principal = as_euint128(op_ctx(&inco_program, &investor), 0)?;
+            safe.bump = ctx.bumps.safe;
+        }
+        safe.principal = e_add(
+            op_ctx(&inco_program, &investor),
+            safe.principal,
+            e_amount,
+            0,
+        )?;
+
+        // 3. Decryption grants: investor for vault and SAFE, company admin and auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.investor_vault.cusd.0, investor_key)?;
+        grants.allow(safe.principal.0, investor_key)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, safe.principal.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Company admin: close the SAFE round at the company's priced offering. Sets the conversion
+    /// price to the better of `valuation_cap / fully_diluted_shares` and the discounted `price_per_share`.
+    pub fn price_safe_round(ctx: Context<PriceSafeRound>, fully_diluted_shares: u64) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        // The round price is the public offering price; a private price cannot anchor a SAFE
+        let round_price = ctx.accounts.company_account.price_per_share;
+        let round = &mut ctx.accounts.safe_round;
+        require!(!round.is_priced(), DonatradeError::SafeRoundPriced);
+        require!(
+            round_price > 0 && fully_diluted_shares > 0,
+            DonatradeError::InvalidSafeTerms
+        );
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::PriceSafeRound {
+                round_id: round.round_id,
+                fully_diluted_shares,
+            },
+        )?;
+        round.conversion_price = round.conversion_price_for(round_price, fully_diluted_shares);
+        Ok(())
+    }
+
+    /// Permissionless crank: convert a SAFE in a priced round into shares at the conversion price.
+    /// Shares come out of the company's pool, capped at what is left in it; principal not
+    /// covered by the shares delivered is refunded from the company balance to the investor's
+    /// vault. The SAFE is closed and its rent returned to the investor.
+    pub fn convert_safe<'info>(ctx: Context<'_, '_, '_, 'info, ConvertSafe<'info>>) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        ctx.accounts.company_account.require_not_paused()?;
+        let round = &ctx.accounts.safe_round;
+        require!(round.is_priced(), DonatradeError::SafeRoundNotPriced);
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let payer = ctx.accounts.payer.to_account_info();
+        let investor_key = ctx.accounts.investor.key();
+
+        let e_owed = e_mul_div(
+            &inco_program,
+            &payer,
+            ctx.accounts.safe.principal,
+            1,
+            round.conversion_price,
+        )?;
+
+        // Shares = min(owed, pool)
+        let company = &mut ctx.accounts.company_account;
+        let fits = e_ge(
+            op_ctx(&inco_program, &payer),
+            company.shares_available,
+            e_owed,
+            0,
+        )?;
+        let e_shares = e_select(
+            op_ctx(&inco_program, &payer),
+            fits,
+            e_owed,
+            company.shares_available,
+            0,
+        )?;
+        company.shares_available = e_sub(
+            op_ctx(&inco_program, &payer),
+            company.shares_available,
+            e_shares,
+            0,
+        )?;
+        track_outstanding(
+            &inco_program,
+            &payer,
+            &mut company.outstanding_shares,
+            e_shares,
+            true,
+        )?;
+
+        let position = &mut ctx.accounts.position;
+        if position.version == 0 {
+            position.version = CURRENT_ACCOUNT_VERSION;
+            position.owner = investor_key;
+            position.company_id = round.company_id;
+            position.bump = ctx.bumps.position;
+        }
+        position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &payer),
+            position.encrypted_shares,
+            e_shares,
+            0,
+        )?;
+
+        // Refund the principal the shares do not cover, as far as the company balance allows
+        let e_price = as_euint128(
+            op_ctx(&inco_program, &payer),
+            round.conversion_price as u128,
+        )?;
+        let e_used = e_mul(op_ctx(&inco_program, &payer), e_shares, e_price, 0)?;
+        let e_uncovered = e_sub(
+            op_ctx(&inco_program, &payer),
+            ctx.accounts.safe.principal,
+            e_used,
+            0,
+        )?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &payer), 0)?;
+        let covered = e_ge(op_ctx(&inco_program, &payer), company.cusd, e_uncovered, 0)?;
+        let e_refund = e_select(
+            op_ctx(&inco_program, &payer),
+            covered,
+            e_uncovered,
+            e_zero,
+            0,
+        )?;
+        company.cusd = e_sub(op_ctx(&inco_program, &payer), company.cusd, e_refund, 0)?;
+        let vault = &mut ctx.accounts.investor_vault;
+        if vault.version == 0 {
+            vault.version = CURRENT_ACCOUNT_VERSION;
+            vault.owner = investor_key;
+            vault.mint = company.quote_mint;
+            vault.bump = ctx.bumps.investor_vault;
+            vault.cusd = e_zero;
+        }
+        vault.cusd = e_add(op_ctx(&inco_program, &payer), vault.cusd, e_refund, 0)?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            payer,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(position.encrypted_shares.0, investor_key)?;
+        grants.allow(company.shares_available.0, company.company_admin)?;
+        grants.allow_auditor(company, position.encrypted_shares.0)?;
+        grants.allow_auditor(company, company.shares_available.0)?;
+        grants.allow(vault.cusd.0, investor_key)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Company admin: move `amount` shares from the company's pool into its option pool.
+    pub fn fund_option_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, FundOptionPool<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::FundOptionPool { amount },
+        )?;
+
+        let e_amount = as_euint128(op_ctx(&inco_program, &admin), amount as u128)?;
+        company.shares_available = e_sub(
+            op_ctx(&inco_program, &admin),
+            company.shares_available,
+            e_amount,
+            0,
+        )?;
+
+        let pool = &mut ctx.accounts.option_pool;
+        pool.company_id = company.company_id;
+        pool.bump = ctx.bumps.option_pool;
+        if pool.available.0 == 0 {
+            pool.available = as_euint128(op_ctx(&inco_program, &admin), 0)?;
+        }
+        pool.available = e_add(op_ctx(&inco_program, &admin), pool.available, e_amount, 0)?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(pool.available.0, company.company_admin)?;
+        grants.allow_auditor(company, pool.available.0)?;
+        Ok(())
+    }
+
+    /// Company admin: grant `e_total` options out of the pool to an employee.
+    /// A grant larger than the pool is issued empty.
+    #[allow(clippy::too_many_arguments)]
+    pub fn issue_option_grant<'info>(
+        ctx: Context<'_, '_, '_, 'info, IssueOptionGrant<'info>>,
+        grant_id: u64,
+        e_total: Euint128,
+        strike_price: u64,
+        vesting_start: i64,
+        cliff_at: i64,
+        vesting_end: i64,
+        expires_at: i64,
+        early_exercise: bool,
+        post_termination_window: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        require!(
+            vesting_start <= cliff_at
+                && cliff_at <= vesting_end
+                && vesting_start < vesting_end
+                && vesting_end <= expires_at
+                && post_termination_window >= 0,
+            DonatradeError::InvalidOptionTerms
+        );
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::IssueOptionGrant {
+                grant_id,
+                employee: ctx.accounts.employee.key(),
+                e_total: e_total.0,
+                strike_price,
+                vesting_start,
+                cliff_at,
+                vesting_end,
+                expires_at,
+                early_exercise,
+                post_termination_window,
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+        let company = &ctx.accounts.company_account;
+        let pool = &mut ctx.accounts.option_pool;
+
+        let e_zero = as_euint128(op_ctx(&inco_program, &admin), 0)?;
+        let fits = e_ge(op_ctx(&inco_program, &admin), pool.available, e_total, 0)?;
+        let e_granted = e_select(op_ctx(&inco_program, &admin), fits, e_total, e_zero, 0)?;
+        pool.available = e_sub(op_ctx(&inco_program, &admin), pool.available, e_granted, 0)?;
+
+        let grant = &mut ctx.accounts.option_grant;
+        grant.employee = ctx.accounts.employee.key();
+        grant.company_id = company.company_id;
+        grant.grant_id = grant_id;
+        grant.total = e_granted;
+        grant.exercised = e_zero;
+        grant.strike_price = strike_price;
+        grant.vesting_start = vesting_start;
+        grant.cliff_at = cliff_at;
+        grant.vesting_end = vesting_end;
+        grant.expires_at = expires_at;
+        grant.early_exercise = early_exercise;
+        grant.terminated_at = 0;
+        grant.post_termination_window = post_termination_window;
+        grant.bump = ctx.bumps.option_grant;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(pool.available.0, company.company_admin)?;
+        grants.allow(grant.total.0, grant.employee)?;
+        grants.allow(grant.total.0, company.company_admin)?;
+        grants.allow_auditor(company, pool.available.0)?;
+        grants.allow_auditor(company, grant.total.0)?;
+        Ok(())
+    }
+
+    /// Company admin: record the employee's termination. Vesting stops and the
+    /// post-termination exercise window starts.
+    pub fn terminate_option_grant(ctx: Context<TerminateOptionGrant>) -> Result<()> {
+        let grant = &mut ctx.accounts.option_grant;
+        require!(
+            !grant.is_terminated(),
+            DonatradeError::OptionGrantTerminated
+        );
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::TerminateOptionGrant {
+                employee: grant.employee,
+                grant_id: grant.grant_id,
+            },
+        )?;
+        grant.terminated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Employee: exercise up to `e_quantity` options, paying the strike in cUSD to the company.
+    /// A request above what is exercisable, or costing more than the vault holds, exercises nothing.
+    pub fn exercise_options<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExerciseOptions<'info>>,
+        e_quantity: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.employee_vault)?;
+        require!(
+            ctx.accounts.agreement_acceptance.covers(company),
+            DonatradeError::AgreementNotAccepted
+        );
+        let grant = &mut ctx.accounts.option_grant;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            grant.exercise_open(now),
+            DonatradeError::ExerciseWindowClosed
+        );
+        record_primary_purchase(
+            &ctx.accounts.platform_config,
+            &mut ctx.accounts.employee_vault,
+            company,
+            now,
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let employee = ctx.accounts.employee.to_account_info();
+        let employee_key = ctx.accounts.employee.key();
+
+        // 1. Exercisable = vested (or everything, for early exercise while employed) - exercised
+        let e_zero = as_euint128(op_ctx(&inco_program, &employee), 0)?;
+        let cutoff = grant.vesting_cutoff(now);
+        let e_vested =
+            if (grant.early_exercise && !grant.is_terminated()) || cutoff >= grant.vesting_end {
+                grant.total
+            } else if cutoff < grant.cliff_at {
+                e_zero
+            } else {
+                e_mul_div(
+                    &inco_program,
+                    &employee,
+                    grant.total,
+                    (cutoff - grant.vesting_start) as u64,
+                    (grant.vesting_end - grant.vesting_start) as u64,
+                )?
+            };
+        let e_exercisable = e_sub(
+            op_ctx(&inco_program, &employee),
+            e_vested,
+            grant.exercised,
+            0,
+        )?;
+        let fits = e_ge(
+            op_ctx(&inco_program, &employee),
+            e_exercisable,
+            e_quantity,
+            0,
+        )?;
+        let e_requested = e_select(
+            op_ctx(&inco_program, &employee),
+            fits,
+            e_quantity,
+            e_zero,
+            0,
+        )?;
+
+        // 2. ... and only if the vault covers the strike
+        let e_strike = as_euint128(op_ctx(&inco_program, &employee), grant.strike_price as u128)?;
+        let e_requested_cost = e_mul(op_ctx(&inco_program, &employee), e_requested, e_strike, 0)?;
+        let affordable = e_ge(
+            op_ctx(&inco_program, &employee),
+            ctx.accounts.employee_vault.cusd,
+            e_requested_cost,
+            0,
+        )?;
+        let e_exercised = e_select(
+            op_ctx(&inco_program, &employee),
+            affordable,
+            e_requested,
+            e_zero,
+            0,
+        )?;
+
+        // 3. Pay the strike from the vault to the company
+        let e_cost = e_mul(op_ctx(&inco_program, &employee), e_exercised, e_strike, 0)?;
+        ctx.accounts.employee_vault.cusd = e_sub(
+            op_ctx(&inco_program, &employee),
+            ctx.accounts.employee_vault.cusd,
+            e_cost,
+            0,
+        )?;
+        company.cusd = e_add(op_ctx(&inco_program, &employee), company.cusd, e_cost, 0)?;
+
+        // 4. Deliver the shares
+        grant.exercised = e_add(
+            op_ctx(&inco_program, &employee),
+            grant.exercised,
+            e_exercised,
+            0,
+        )?;
+        let position = &mut ctx.accounts.position;
+        if position.version == 0 {
+            position.version = CURRENT_ACCOUNT_VERSION;
+            position.owner = employee_key;
+            position.company_id = company.company_id;
+            position.bump = ctx.bumps.position;
+        }
+        position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &employee),
+            position.encrypted_shares,
+            e_exercised,
+            0,
+        )?;
+        track_outstanding(
+            &inco_program,
+            &employee,
+            &mut company.outstanding_shares,
+            e_exercised,
+            true,
+        )?;
+
+        // 5. Decryption grants: employee, company admin, then auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            employee,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.employee_vault.cusd.0, employee_key)?;
+        grants.allow(position.encrypted_shares.0, employee_key)?;
+        grants.allow(grant.exercised.0, employee_key)?;
+        grants.allow(grant.exercised.0, company.company_admin)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, position.encrypted_shares.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Company admin: once a grant can no longer be exercised, return its unexercised
+    /// options to the pool and close it.
+    pub fn reclaim_option_grant<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReclaimOptionGrant<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let grant = &ctx.accounts.option_grant;
+        require!(
+            !grant.exercise_open(Clock::get()?.unix_timestamp),
+            DonatradeError::ExerciseWindowOpen
+        );
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::ReclaimOptionGrant {
+                employee: grant.employee,
+                grant_id: grant.grant_id,
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+        let pool = &mut ctx.accounts.option_pool;
+
+        let e_unexercised = e_sub(
+            op_ctx(&inco_program, &admin),
+            grant.total,
+            grant.exercised,
+            0,
+        )?;
+        pool.available = e_add(
+            op_ctx(&inco_program, &admin),
+            pool.available,
+            e_unexercised,
+            0,
+        )?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let company = &ctx.accounts.company_account;
+        grants.allow(pool.available.0, company.company_admin)?;
+        grants.allow_auditor(company, pool.available.0)?;
+        Ok(())
+    }
+
+    /// Company admin: define a new share class with an empty pool, inactive until
+    /// `update_share_class` adds shares and opens it.
+    pub fn create_share_class(
+        ctx: Context<CreateShareClass>,
+        class_id: u8,
+        kind: ShareClassKind,
+        conversion_ratio: u64,
+        liquidation_multiple_bps: u32,
+        participating: bool,
+        seniority: u8,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        require!(
+            class_id != COMMON_CLASS_ID
+                && (kind == ShareClassKind::Preferred
+                    || (conversion_ratio == 0 && liquidation_multiple_bps == 0)),
+            DonatradeError::InvalidShareClass
+        );
+        authorize_company_action(
+            &ctx.accounts.company_account,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::CreateShareClass {
+                class_id,
+                kind,
+                conversion_ratio,
+                liquidation_multiple_bps,
+                participating,
+                seniority,
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        let class = &mut ctx.accounts.share_class;
+        class.company_id = ctx.accounts.company_account.company_id;
+        class.class_id = class_id;
+        class.kind = kind;
+        class.shares_available = as_euint128(op_ctx(&inco_program, &admin), 0)?;
+        class.price_per_share = 0;
+        class.transferable = false;
+        class.conversion_ratio = conversion_ratio;
+        class.liquidation_multiple_bps = liquidation_multiple_bps;
+        class.participating = participating;
+        class.seniority = seniority;
+        class.active = false;
+        class.bump = ctx.bumps.share_class;
+        class.outstanding_shares = class.shares_available;
+        Ok(())
+    }
+
+    /// Company admin: update a share class's price, transfer rule and status and add shares to its pool.
+    pub fn update_share_class(
+        ctx: Context<UpdateShareClass>,
+        price_per_share: u64,
+        add_shares: u64,
+        transferable: bool,
+        active: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &ctx.accounts.company_account;
+        company.require_not_paused()?;
+        let class = &mut ctx.accounts.share_class;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::UpdateShareClass {
+                class_id: class.class_id,
+                price_per_share,
+                add_shares,
+                transferable,
+                active,
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        let e_add_shares = as_euint128(op_ctx(&inco_program, &admin), add_shares as u128)?;
+        class.shares_available = e_add(
+            op_ctx(&inco_program, &admin),
+            class.shares_available,
+            e_add_shares,
+            0,
+        )?;
+        class.price_per_share = price_per_share;
+        class.transferable = transferable;
+        class.active = active;
+        Ok(())
+    }
+
+    /// Holder: convert preferred shares into common at the class's conversion ratio.
+    /// Not while common stock is frozen for an open rights period.
+    pub fn convert_share_class<'info>(
+        ctx: Context<'_, '_, '_, 'info, ConvertShareClass<'info>>,
+        e_shares: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Secondary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        // New common stock could otherwise be snapshotted for rights mid-period
+        require_transferable(company, COMMON_CLASS_ID, None)?;
+        let class = &mut ctx.accounts.share_class;
+        require!(
+            class.kind == ShareClassKind::Preferred && class.conversion_ratio > 0,
+            DonatradeError::InvalidShareClass
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let holder = ctx.accounts.holder.to_account_info();
+        let holder_key = ctx.accounts.holder.key();
+
+        ctx.accounts.preferred_position.encrypted_shares = e_sub(
+            op_ctx(&inco_program, &holder),
+            ctx.accounts.preferred_position.encrypted_shares,
+            e_shares,
+            0,
+        )?;
+        let e_ratio = as_euint128(
+            op_ctx(&inco_program, &holder),
+            class.conversion_ratio as u128,
+        )?;
+        let e_common = e_mul(op_ctx(&inco_program, &holder), e_shares, e_ratio, 0)?;
+        track_outstanding(
+            &inco_program,
+            &holder,
+            &mut class.outstanding_shares,
+            e_shares,
+            false,
+        )?;
+        track_outstanding(
+            &inco_program,
+            &holder,
+            &mut company.outstanding_shares,
+            e_common,
+            true,
+        )?;
+
+        let common_position = &mut ctx.accounts.common_position;
+        if common_position.version == 0 {
+            common_position.version = CURRENT_ACCOUNT_VERSION;
+            common_position.owner = holder_key;
+            common_position.company_id = company.company_id;
+            common_position.class_id = COMMON_CLASS_ID;
+            common_position.bump = ctx.bumps.common_position;
+        }
+        common_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &holder),
+            common_position.encrypted_shares,
+            e_common,
+            0,
+        )?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            holder,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(
+            ctx.accounts.preferred_position.encrypted_shares.0,
+            holder_key,
+        )?;
+        grants.allow(common_position.encrypted_shares.0, holder_key)?;
+        grants.allow_auditor(company, ctx.accounts.preferred_position.encrypted_shares.0)?;
+        grants.allow_auditor(company, common_position.encrypted_shares.0)?;
+        Ok(())
+    }
+
+    /// Company admin: offer to buy back up to `max_shares` common shares at `price_per_share`
+    /// until `closes_at`. The full budget is escrowed from the company balance; if the balance
+    /// falls short nothing is escrowed and every tender is returned unbought.
+    pub fn create_tender_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateTenderOffer<'info>>,
+        tender_id: u64,
+        max_shares: u64,
+        price_per_share: u64,
+        closes_at: i64,
+        retire: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::CreateTenderOffer {
+                tender_id,
+                max_shares,
+                price_per_share,
+                closes_at,
+                retire,
+            },
+        )?;
+        require!(
+            max_shares > 0 && price_per_share > 0 && closes_at > Clock::get()?.unix_timestamp,
+            DonatradeError::InvalidTenderOffer
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        // Escrow the budget, nothing if the balance falls short; settlement never pays
+        // beyond the escrow
+        let budget = max_shares as u128 * price_per_share as u128;
+        let e_budget = as_euint128(op_ctx(&inco_program, &admin), budget)?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &admin), 0)?;
+        let covered = e_ge(op_ctx(&inco_program, &admin), company.cusd, e_budget, 0)?;
+        let e_escrow = e_select(op_ctx(&inco_program, &admin), covered, e_budget, e_zero, 0)?;
+        company.cusd = e_sub(op_ctx(&inco_program, &admin), company.cusd, e_escrow, 0)?;
+
+        let tender_offer = &mut ctx.accounts.tender_offer;
+        tender_offer.company_id = company.company_id;
+        tender_offer.tender_id = tender_id;
+        tender_offer.max_shares = max_shares;
+        tender_offer.price_per_share = price_per_share;
+        tender_offer.closes_at = closes_at;
+        tender_offer.retire = retire;
+        tender_offer.total_tendered = e_zero;
+        tender_offer.repurchased = e_zero;
+        tender_offer.escrow = e_escrow;
+        tender_offer.tender_count = 0;
+        tender_offer.settled_count = 0;
+        tender_offer.finalized = false;
+        tender_offer.tendered_shares = 0;
+        tender_offer.total_revealed = false;
+        tender_offer.bump = ctx.bumps.tender_offer;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Holder: tender encrypted common shares into an open tender offer. Tendered shares
+    /// leave the position until settlement.
+    pub fn tender_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, TenderShares<'info>>,
+        e_amount: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &ctx.accounts.company_account;
+        company.require_not_paused()?;
+        let tender_offer = &mut ctx.accounts.tender_offer;
+        require!(
+            Clock::get()?.unix_timestamp < tender_offer.closes_at,
+            DonatradeError::TenderWindowClosed
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let holder = ctx.accounts.holder.to_account_info();
+        let holder_key = ctx.accounts.holder.key();
+
+        // 1. Tender nothing unless the position covers the amount
+        let e_zero = as_euint128(op_ctx(&inco_program, &holder), 0)?;
+        let covered = e_ge(
+            op_ctx(&inco_program, &holder),
+            ctx.accounts.position.encrypted_shares,
+            e_amount,
+            0,
+        )?;
+        let e_tendered = e_select(op_ctx(&inco_program, &holder), covered, e_amount, e_zero, 0)?;
+        ctx.accounts.position.encrypted_shares = e_sub(
+            op_ctx(&inco_program, &holder),
+            ctx.accounts.position.encrypted_shares,
+            e_tendered,
+            0,
+        )?;
+        tender_offer.total_tendered = e_add(
+            op_ctx(&inco_program, &holder),
+            tender_offer.total_tendered,
+            e_tendered,
+            0,
+        )?;
+
+        // 2. Add to the holder's tender
+
+        let tender = &mut ctx.accounts.tender;
+        if tender.holder == Pubkey::default() {
+            tender.holder = holder_key;
+            tender.tender_offer = tender_offer.key();
+            tender.amount = e_zero;
+            tender.bump = ctx.bumps.tender;
+            tender_offer.tender_count += 1;
+        }
+        tender.amount = e_add(op_ctx(&inco_program, &holder), tender.amount, e_tendered, 0)?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            holder,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.position.encrypted_shares.0, holder_key)?;
+        grants.allow(tender.amount.0, holder_key)?;
+        grants.allow(tender_offer.total_tendered.0, company.company_admin)?;
+        grants.allow_auditor(company, ctx.accounts.position.encrypted_shares.0)?;
+        Ok(())
+    }
+
+    /// Permissionless after the window: record the tendered total from an attested decryption
+    /// of `total_tendered` (granted to the company admin), so settlement can prorate against
+    /// a public divisor.
+    pub fn reveal_tender_total(ctx: Context<RevealTenderTotal>, total: u64) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let tender_offer = &mut ctx.accounts.tender_offer;
+        require!(
+            Clock::get()?.unix_timestamp >= tender_offer.closes_at,
+            DonatradeError::TenderWindowOpen
+        );
+        require!(
+            !tender_offer.finalized && !tender_offer.total_revealed,
+            DonatradeError::InvalidTenderOffer
+        );
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.payer,
+            &[(tender_offer.total_tendered.0, total as u128)],
+        )?;
+        tender_offer.tendered_shares = total;
+        tender_offer.total_revealed = true;
+        Ok(())
+    }
+
+    /// Permissionless crank once the total is revealed: settle one tender. If the offer is
+    /// oversubscribed the tender is accepted pro-rata (`amount * max_shares / tendered_shares`);
+    /// accepted shares are paid into the vault and the rest return to the position. Once the
+    /// offer is finalized the whole tender is returned.
+    pub fn settle_tender<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleTender<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let tender_offer = &mut ctx.accounts.tender_offer;
+        require_quote_mint(&ctx.accounts.company_account, &ctx.accounts.holder_vault)?;
+        require!(
+            Clock::get()?.unix_timestamp >= tender_offer.closes_at,
+            DonatradeError::TenderWindowOpen
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let payer = ctx.accounts.payer.to_account_info();
+        let amount = ctx.accounts.tender.amount;
+
+        // 1. Accepted = amount, prorated when total tendered exceeds the cap
+        let e_accepted = if tender_offer.finalized {
+            as_euint128(op_ctx(&inco_program, &payer), 0)?
+        } else {
+            require!(
+                tender_offer.total_revealed,
+                DonatradeError::TenderTotalUnrevealed
+            );
+            if tender_offer.tendered_shares > tender_offer.max_shares {
+                e_mul_div(
+                    &inco_program,
+                    &payer,
+                    amount,
+                    tender_offer.max_shares,
+                    tender_offer.tendered_shares,
+                )?
+            } else {
+                amount
+            }
+        };
+
+        // 2. Pay for accepted shares, return the rest; if the escrow cannot pay, nothing is bought
+        let e_price = as_euint128(
+            op_ctx(&inco_program, &payer),
+            tender_offer.price_per_share as u128,
+        )?;
+        let e_cost = e_mul(op_ctx(&inco_program, &payer), e_accepted, e_price, 0)?;
+        let funded = e_ge(
+            op_ctx(&inco_program, &payer),
+            tender_offer.escrow,
+            e_cost,
+            0,
+        )?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &payer), 0)?;
+        let e_accepted = e_select(op_ctx(&inco_program, &payer), funded, e_accepted, e_zero, 0)?;
+        let e_paid = e_select(op_ctx(&inco_program, &payer), funded, e_cost, e_zero, 0)?;
+        let e_returned = e_sub(op_ctx(&inco_program, &payer), amount, e_accepted, 0)?;
+        tender_offer.escrow = e_sub(
+            op_ctx(&inco_program, &payer),
+            tender_offer.escrow,
+            e_paid,
+            0,
+        )?;
+        tender_offer.repurchased = e_add(
+            op_ctx(&inco_program, &payer),
+            tender_offer.repurchased,
+            e_accepted,
+            0,
+        )?;
+        ctx.accounts.holder_vault.cusd = e_add(
+            op_ctx(&inco_program, &payer),
+            ctx.accounts.holder_vault.cusd,
+            e_paid,
+            0,
+        )?;
+        ctx.accounts.holder_position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &payer),
+            ctx.accounts.holder_position.encrypted_shares,
+            e_returned,
+            0,
+        )?;
+        tender_offer.settled_count += 1;
+
+        let holder_key = ctx.accounts.holder.key();
+        let mut grants = HandleGrants::new(
+            inco_program,
+            payer,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.holder_vault.cusd.0, holder_key)?;
+        grants.allow(ctx.accounts.holder_position.encrypted_shares.0, holder_key)?;
+        grants.allow_auditor(
+            &ctx.accounts.company_account,
+            ctx.accounts.holder_position.encrypted_shares.0,
+        )?;
+        Ok(())
+    }
+
+    /// Permissionless once every tender is settled, or once `TENDER_SETTLEMENT_GRACE` has passed
+    /// since the close: return the unspent budget to the company and either retire the
+    /// repurchased shares or put them back in the pool.
+    pub fn finalize_tender_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, FinalizeTenderOffer<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let tender_offer = &mut ctx.accounts.tender_offer;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= tender_offer.closes_at,
+            DonatradeError::TenderWindowOpen
+        );
+        require!(
+            !tender_offer.finalized
+                && (tender_offer.settled_count == tender_offer.tender_count
+                    || now
+                        >= tender_offer
+                            .closes_at
+                            .saturating_add(TENDER_SETTLEMENT_GRACE)),
+            DonatradeError::TendersUnsettled
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let payer = ctx.accounts.payer.to_account_info();
+        let company = &mut ctx.accounts.company_account;
+
+        company.cusd = e_add(
+            op_ctx(&inco_program, &payer),
+            company.cusd,
+            tender_offer.escrow,
+            0,
+        )?;
+        tender_offer.escrow = as_euint128(op_ctx(&inco_program, &payer), 0)?;
+        if !tender_offer.retire {
+            company.shares_available = e_add(
+                op_ctx(&inco_program, &payer),
+                company.shares_available,
+                tender_offer.repurchased,
+                0,
+            )?;
+        }
+        track_outstanding(
+            &inco_program,
+            &payer,
+            &mut company.outstanding_shares,
+            tender_offer.repurchased,
+            false,
+        )?;
+        tender_offer.finalized = true;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            payer,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow(tender_offer.repurchased.0, company.company_admin)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Holder: record a pro-rata entitlement for the open rights round from the current
+    /// common position (`held * new_shares / outstanding_shares`). Common stock cannot change
+    /// hands until the period ends, so each share backs at most one entitlement.
+    pub fn snapshot_rights<'info>(
+        ctx: Context<'_, '_, '_, 'info, SnapshotRights<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &ctx.accounts.company_account;
+        company.require_not_paused()?;
+        let rights = company.rights_period;
+        require!(
+            rights.is_open(Clock::get()?.unix_timestamp),
+            DonatradeError::RightsPeriodClosed
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let holder = ctx.accounts.holder.to_account_info();
+        let holder_key = ctx.accounts.holder.key();
+
+        let e_entitled = e_mul_div(
+            &inco_program,
+            &holder,
+            ctx.accounts.position.encrypted_shares,
+            rights.new_shares,
+            rights.outstanding_shares,
+        )?;
+        let entitlement = &mut ctx.accounts.entitlement;
+        entitlement.holder = holder_key;
+        entitlement.company_id = company.company_id;
+        entitlement.round = rights.round;
+        entitlement.remaining = e_entitled;
+        entitlement.bump = ctx.bumps.entitlement;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            holder,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(entitlement.remaining.0, holder_key)?;
+        grants.allow(entitlement.remaining.0, company.company_admin)?;
+        grants.allow_auditor(company, entitlement.remaining.0)?;
+        Ok(())
+    }
+
+    /// Holder: buy common shares during the rights period, up to the remaining entitlement.
+    /// Requests above the entitlement buy nothing.
+    pub fn exercise_rights<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExerciseRights<'info>>,
+        e_shares: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.holder_vault)?;
+        require!(company.active, DonatradeError::Inactive);
+        require!(
+            ctx.accounts.agreement_acceptance.covers(company),
+            DonatradeError::AgreementNotAccepted
+        );
+        let now = Clock::get()?.unix_timestamp;
+        record_primary_purchase(
+            &ctx.accounts.platform_config,
+            &mut ctx.accounts.holder_vault,
+            company,
+            now,
+        )?;
+        require!(
+            company.rights_period.is_open(now),
+            DonatradeError::RightsPeriodClosed
+        );
+        let entitlement = &mut ctx.accounts.entitlement;
+        require!(
+            entitlement.round == company.rights_period.round,
+            DonatradeError::RightsPeriodClosed
+        );
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let holder = ctx.accounts.holder.to_account_info();
+        let holder_key = ctx.accounts.holder.key();
+
+        // 1. Cap the purchase at the remaining entitlement
+        let e_zero = as_euint128(op_ctx(&inco_program, &holder), 0)?;
+        let fits = e_ge(
+            op_ctx(&inco_program, &holder),
+            entitlement.remaining,
+            e_shares,
+            0,
+        )?;
+        let e_bought = e_select(op_ctx(&inco_program, &holder), fits, e_shares, e_zero, 0)?;
+        entitlement.remaining = e_sub(
+            op_ctx(&inco_program, &holder),
+            entitlement.remaining,
+            e_bought,
+            0,
+        )?;
+
+        // 2. Take the shares from the pool and charge the current price
+        company.shares_available = e_sub(
+            op_ctx(&inco_program, &holder),
+            company.shares_available,
+            e_bought,
+            0,
+        )?;
+        let e_price = price_handle(
+            &inco_program,
+            &holder,
+            company.private_pricing,
+            company.encrypted_price,
+            company.current_price(now),
+        )?;
+        let e_cost = e_mul(op_ctx(&inco_program, &holder), e_price, e_bought, 0)?;
+        ctx.accounts.holder_vault.cusd = e_sub(
+            op_ctx(&inco_program, &holder),
+            ctx.accounts.holder_vault.cusd,
+            e_cost,
+            0,
+        )?;
+        company.cusd = e_add(op_ctx(&inco_program, &holder), company.cusd, e_cost, 0)?;
+
+        // 3. Deliver the shares
+        ctx.accounts.position.encrypted_shares = e_add(
+            op_ctx(&inco_program, &holder),
+            ctx.accounts.position.encrypted_shares,
+            e_bought,
+            0,
+        )?;
+        track_outstanding(
+            &inco_program,
+            &holder,
+            &mut company.outstanding_shares,
+            e_bought,
+            true,
+        )?;
+
+        // 4. Decryption grants: holder, company admin, then auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            holder,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.holder_vault.cusd.0, holder_key)?;
+        grants.allow(ctx.accounts.position.encrypted_shares.0, holder_key)?;
+        grants.allow(entitlement.remaining.0, holder_key)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow(company.shares_available.0, company.company_admin)?;
+        grants.allow_auditor(company, ctx.accounts.position.encrypted_shares.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Donor: give encrypted cUSD from the vault to a company without receiving shares.
+    /// The amount is added to the donor's cumulative `DonorRecord`.
+    pub fn donate<'info>(
+        ctx: Context<'_, '_, '_, 'info, Donate<'info>>,
+        e_amount: Euint128,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_quote_mint(company, &ctx.accounts.donor_vault)?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let donor = ctx.accounts.donor.to_account_info();
+        let donor_key = ctx.accounts.donor.key();
+
+        // 1. Move the donation from the vault to the company
+        ctx.accounts.donor_vault.cusd = e_sub(
+            op_ctx(&inco_program, &donor),
+            ctx.accounts.donor_vault.cusd,
+            e_amount,
+            0,
+        )?;
+        company.cusd = e_add(op_ctx(&inco_program, &donor), company.cusd, e_amount, 0)?;
+
+        // 2. Accumulate the donor record
+        let record = &mut ctx.accounts.donor_record;
+        if record.donor == Pubkey::default() {
+            record.donor = donor_key;
+            record.company_id = company.company_id;
+            record.total = as_euint128(op_ctx(&inco_program, &donor), 0)?;
+            record.bump = ctx.bumps.donor_record;
+        }
+        record.total = e_add(op_ctx(&inco_program, &donor), record.total, e_amount, 0)?;
+        record.donation_count += 1;
+
+        // 3. Decryption grants: donor, company admin, then auditor
+        let mut grants = HandleGrants::new(
+            inco_program,
+            donor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.donor_vault.cusd.0, donor_key)?;
+        grants.allow(record.total.0, donor_key)?;
+        grants.allow(record.total.0, company.company_admin)?;
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, record.total.0)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        Ok(())
+    }
+
+    /// Donor: opt in to the public leaderboard by revealing the cumulative total,
+    /// backed by an attested decryption of the record's handle.
+    pub fn publish_donor_total(ctx: Context<PublishDonorTotal>, total: u64) -> Result<()> {
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.donor,
+            &[(ctx.accounts.donor_record.total.0, total as u128)],
+        )?;
+        let record = &mut ctx.accounts.donor_record;
+        record.public = true;
+        record.public_total = total;
+        Ok(())
+    }
+
+    /// Donor: leave the public leaderboard.
+    pub fn hide_donor_total(ctx: Context<HideDonorTotal>) -> Result<()> {
+        let record = &mut ctx.accounts.donor_record;
+        record.public = false;
+        record.public_total = 0;
+        Ok(())
+    }
+
+    /// Platform admin: accept a quote mint for deposits, held in a `GlobalProgramVault`-owned
+    /// token account. `legacy_usdc` registers the mint behind the original unkeyed USDC vaults.
+    pub fn register_quote_mint(ctx: Context<RegisterQuoteMint>, legacy_usdc: bool) -> Result<()> {
+        if legacy_usdc {
+            require_keys_eq!(
+                ctx.accounts.vault_token_account.key(),
+                ctx.accounts.global_vault.usdc_token_account,
+                DonatradeError::InvalidQuoteMint
+            );
+        }
+
+        let quote_mint = &mut ctx.accounts.quote_mint;
+        quote_mint.mint = ctx.accounts.mint.key();
+        quote_mint.vault_token_account = ctx.accounts.vault_token_account.key();
+        quote_mint.token_program = ctx.accounts.token_program.key();
+        quote_mint.decimals = ctx.accounts.mint.decimals;
+        quote_mint.balance_key = if legacy_usdc {
+            Pubkey::default()
+        } else {
+            ctx.accounts.mint.key()
+        };
+        quote_mint.active = true;
+        quote_mint.bump = ctx.bumps.quote_mint;
+        Ok(())
+    }
+
+    /// Platform admin: stop or resume deposits in a quote mint. Withdrawals stay open.
+    pub fn set_quote_mint_active(ctx: Context<SetQuoteMintActive>, active: bool) -> Result<()> {
+        ctx.accounts.quote_mint.active = active;
+        Ok(())
+    }
+
+    /// Investor: debit `amount` from the vault into a pending withdrawal, released to
+    /// `investor_token_account` after the platform's withdrawal delay.
+    pub fn request_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestWithdrawal<'info>>,
+        request_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let config = &ctx.accounts.platform_config;
+        config.require_not_paused(PauseScope::Withdrawals)?;
+        require!(
+            config.delayed_withdrawals(),
+            DonatradeError::WithdrawalNotDelayed
+        );
+        require_keys_eq!(
+            ctx.accounts.investor_vault.mint,
+            ctx.accounts.quote_mint.balance_key,
+            DonatradeError::QuoteMintMismatch
+        );
+        let limits = config.velocity_limits_for(ctx.accounts.investor_vault.velocity_override);
+        ctx.accounts.investor_vault.withdrawals.record(
+            Clock::get()?.unix_timestamp,
+            amount,
+            limits.withdrawal_limit,
+            DonatradeError::WithdrawalLimitExceeded,
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let investor = ctx.accounts.investor.to_account_info();
+        let investor_key = ctx.accounts.investor.key();
+
+        // Debit only a balance that covers the amount; release needs the attested flag
+        let e_amount = as_euint128(op_ctx(&inco_program, &investor), amount as u128)?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &investor), 0)?;
+        let covered = e_ge(
+            op_ctx(&inco_program, &investor),
+            ctx.accounts.investor_vault.cusd,
+            e_amount,
+            0,
+        )?;
+        let e_debited = e_select(
+            op_ctx(&inco_program, &investor),
+            covered,
+            e_amount,
+            e_zero,
+            0,
+        )?;
+        ctx.accounts.investor_vault.cusd = e_sub(
+            op_ctx(&inco_program, &investor),
+            ctx.accounts.investor_vault.cusd,
+            e_debited,
+            0,
+        )?;
+
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        pending.requester = investor_key;
+        pending.request_id = request_id;
+        pending.from_company = false;
+        pending.company_id = 0;
+        pending.mint = ctx.accounts.quote_mint.mint;
+        pending.balance_key = ctx.accounts.quote_mint.balance_key;
+        pending.destination = ctx.accounts.investor_token_account.key();
+        pending.amount = amount;
+        pending.covered = covered;
+        pending.release_at = Clock::get()?.unix_timestamp + config.withdrawal_delay;
+        pending.frozen = false;
+        pending.bump = ctx.bumps.pending_withdrawal;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            investor,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.investor_vault.cusd.0, investor_key)?;
+        grants.allow(covered.0, investor_key)?;
+        Ok(())
+    }
+
+    /// Company admin: debit `amount` from the company balance into a pending withdrawal.
+    /// Approved like `withdraw_company_funds` when the company has a multisig.
+    pub fn request_company_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestCompanyWithdrawal<'info>>,
+        request_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let config = &ctx.accounts.platform_config;
+        config.require_not_paused(PauseScope::Withdrawals)?;
+        require!(
+            config.delayed_withdrawals(),
+            DonatradeError::WithdrawalNotDelayed
+        );
+        let company = &mut ctx.accounts.company_account;
+        company.require_not_paused()?;
+        require_keys_eq!(
+            company.quote_mint,
+            ctx.accounts.quote_mint.balance_key,
+            DonatradeError::QuoteMintMismatch
+        );
+        let limits = config.velocity_limits_for(company.velocity_override);
+        company.withdrawals.record(
+            Clock::get()?.unix_timestamp,
+            amount,
+            limits.withdrawal_limit,
+            DonatradeError::WithdrawalLimitExceeded,
+        )?;
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::WithdrawFunds {
+                amount,
+                destination: ctx.accounts.admin_token_account.key(),
+            },
+        )?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+
+        let e_amount = as_euint128(op_ctx(&inco_program, &admin), amount as u128)?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &admin), 0)?;
+        let covered = e_ge(op_ctx(&inco_program, &admin), company.cusd, e_amount, 0)?;
+        let e_debited = e_select(op_ctx(&inco_program, &admin), covered, e_amount, e_zero, 0)?;
+        company.cusd = e_sub(op_ctx(&inco_program, &admin), company.cusd, e_debited, 0)?;
+
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        pending.requester = ctx.accounts.company_admin.key();
+        pending.request_id = request_id;
+        pending.from_company = true;
+        pending.company_id = company.company_id;
+        pending.mint = ctx.accounts.quote_mint.mint;
+        pending.balance_key = ctx.accounts.quote_mint.balance_key;
+        pending.destination = ctx.accounts.admin_token_account.key();
+        pending.amount = amount;
+        pending.covered = covered;
+        pending.release_at = Clock::get()?.unix_timestamp + config.withdrawal_delay;
+        pending.frozen = false;
+        pending.bump = ctx.bumps.pending_withdrawal;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        grants.allow(covered.0, pending.requester)?;
+        Ok(())
+    }
+
+    /// Permissionless crank: pay out a pending withdrawal once its delay has passed,
+    /// unless compliance froze it. Needs an attested decryption of `covered`; a request the
+    /// balance did not cover debited nothing and is closed without a transfer.
+    /// Rent returns to the requester.
+    pub fn release_withdrawal(ctx: Context<ReleaseWithdrawal>, covered: bool) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Withdrawals)?;
+        let pending = &ctx.accounts.pending_withdrawal;
+        require!(!pending.frozen, DonatradeError::WithdrawalFrozen);
+        require!(
+            Clock::get()?.unix_timestamp >= pending.release_at,
+            DonatradeError::WithdrawalLocked
+        );
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.payer,
+            &[(pending.covered.0, covered as u128)],
+        )?;
+        if !covered {
+            return Ok(());
+        }
+
+        let seeds = &[
+            b"vault_authority".as_ref(),
+            &[ctx.accounts.global_vault.bump],
+        ];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.global_vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            pending.amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        Ok(())
+    }
+
+    /// Compliance: hold (or release the hold on) a pending withdrawal.
+    pub fn freeze_withdrawal(ctx: Context<FreezeWithdrawal>, frozen: bool) -> Result<()> {
+        ctx.accounts.pending_withdrawal.frozen = frozen;
+        Ok(())
+    }
+
+    /// Compliance: cancel a pending withdrawal and credit what was debited back to the
+    /// vault or company it came from.
+    pub fn cancel_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelWithdrawal<'info>>,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_withdrawal;
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let compliance = ctx.accounts.compliance.to_account_info();
+
+        let e_requested = as_euint128(op_ctx(&inco_program, &compliance), pending.amount as u128)?;
+        let e_zero = as_euint128(op_ctx(&inco_program, &compliance), 0)?;
+        let e_amount = e_select(
+            op_ctx(&inco_program, &compliance),
+            pending.covered,
+            e_requested,
+            e_zero,
+            0,
+        )?;
+        let mut grants = HandleGrants::new(
+            inco_program.clone(),
+            compliance.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        if pending.from_company {
+            let company = ctx
+                .accounts
+                .company_account
+                .as_mut()
+                .ok_or(DonatradeError::WithdrawalSourceRequired)?;
+            require!(
+                company.company_id == pending.company_id,
+                DonatradeError::Unauthorized
+            );
+            company.cusd = e_add(
+                op_ctx(&inco_program, &compliance),
+                company.cusd,
+                e_amount,
+                0,
+            )?;
+            grants.allow(company.cusd.0, company.company_admin)?;
+            grants.allow_auditor(company, company.cusd.0)?;
+        } else {
+            let vault = ctx
+                .accounts
+                .investor_vault
+                .as_mut()
+                .ok_or(DonatradeError::WithdrawalSourceRequired)?;
+            require!(
+                vault.owner == pending.requester && vault.mint == pending.balance_key,
+                DonatradeError::Unauthorized
+            );
+            vault.cusd = e_add(op_ctx(&inco_program, &compliance), vault.cusd, e_amount, 0)?;
+            grants.allow(vault.cusd.0, vault.owner)?;
+        }
+        Ok(())
+    }
+
+    /// Company admin: grant itself decryption of the outstanding share counts an exit is
+    /// declared from, for common and for each `ShareClass` account passed first in the
+    /// remaining accounts (`class_count` of them), followed by the decryption grant pairs.
+    pub fn request_exit_share_counts<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestExitShareCounts<'info>>,
+        class_count: u8,
+    ) -> Result<()> {
+        let company = &ctx.accounts.company_account;
+        require!(!company.exited, DonatradeError::CompanyExited);
+        require!(
+            ctx.remaining_accounts.len() >= class_count as usize,
+            DonatradeError::ShareClassRequired
+        );
+        let (class_accounts, grant_accounts) =
+            ctx.remaining_accounts.split_at(class_count as usize);
+
+        let mut grants = HandleGrants::new(
+            ctx.accounts.inco_lightning_program.to_account_info(),
+            ctx.accounts.company_admin.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            grant_accounts,
+        );
+        grants.allow(company.outstanding_shares.0, company.company_admin)?;
+        for info in class_accounts {
+            let class = load_share_class(company, info)?;
+            grants.allow(class.outstanding_shares.0, company.company_admin)?;
+        }
+        Ok(())
+    }
+
+    /// Company admin with platform admin co-signature: record an acquisition.
+    /// `proceeds` cUSD move out of the company balance and the waterfall fixes a per-share payout
+    /// for every listed class. Needs attested decryptions of the company balance (`balance`,
+    /// which must cover the proceeds) and of each class's tracked outstanding count
+    /// (see `request_exit_share_counts`), so neither is taken on trust. `ShareClass` accounts
+    /// for the non-common classes come first in the remaining accounts, in `classes` order,
+    /// followed by the decryption grant pairs. All trading in the company stops for good.
+    pub fn declare_exit<'info>(
+        ctx: Context<'_, '_, '_, 'info, DeclareExit<'info>>,
+        proceeds: u64,
+        balance: u64,
+        classes: Vec<ExitClassShares>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let company = &mut ctx.accounts.company_account;
+        require!(!company.exited, DonatradeError::CompanyExited);
+        require!(
+            !classes.is_empty() && classes.len() <= MAX_EXIT_CLASSES,
+            DonatradeError::InvalidExit
+        );
+        require!(balance >= proceeds, DonatradeError::InsufficientFunds);
+        authorize_company_action(
+            company,
+            ctx.accounts.proposal.as_mut(),
+            &CompanyAction::DeclareExit { proceeds },
+        )?;
+
+        // 1. Build the tranches from the attested cap table
+        let mut class_accounts = ctx.remaining_accounts.iter();
+        let mut tranches = Vec::with_capacity(classes.len());
+        let mut decryptions = vec![(company.cusd.0, balance as u128)];
+        for (i, declared) in classes.iter().enumerate() {
+            require!(
+                declared.outstanding > 0
+                    && !classes[..i]
+                        .iter()
+                        .any(|other| other.class_id == declared.class_id),
+                DonatradeError::InvalidExit
+            );
+            if declared.class_id == COMMON_CLASS_ID {
+                require!(
+                    company.outstanding_shares.0 != 0,
+                    DonatradeError::ShareCountUntracked
+                );
+                decryptions.push((company.outstanding_shares.0, declared.outstanding as u128));
+                tranches.push(Tranche {
+                    outstanding: declared.outstanding,
+                    preference_per_share: 0,
+                    seniority: 0,
+                    participating: true,
+                    as_converted: 1,
+                });
+                continue;
+            }
+
+            let info = class_accounts
+                .next()
+                .ok_or(DonatradeError::ShareClassRequired)?;
+            let class = load_share_class(company, info)?;
+            require!(
+                class.class_id == declared.class_id,
+                DonatradeError::InvalidShareClass
+            );
+            require!(
+                class.outstanding_shares.0 != 0,
+                DonatradeError::ShareCountUntracked
+            );
+            decryptions.push((class.outstanding_shares.0, declared.outstanding as u128));
+            let preference =
+                class.price_per_share as u128 * class.liquidation_multiple_bps as u128 / 10_000;
+            tranches.push(Tranche {
+                outstanding: declared.outstanding,
+                preference_per_share: preference as u64,
+                seniority: class.seniority,
+                participating: class.participating,
+                as_converted: class.conversion_ratio.max(1),
+            });
+        }
+        verify_decryption(
+            &ctx.accounts.inco_lightning_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.company_admin,
+            &decryptions,
+        )?;
+        let payouts = exit_waterfall(proceeds, &tranches);
+
+        // 2. Escrow the proceeds out of the company balance, which the attestation shows covers them
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let admin = ctx.accounts.company_admin.to_account_info();
+        let e_proceeds = as_euint128(op_ctx(&inco_program, &admin), proceeds as u128)?;
+        company.cusd = e_sub(op_ctx(&inco_program, &admin), company.cusd, e_proceeds, 0)?;
+        company.exited = true;
+        company.active = false;
+
+        // 3. Record the per-share payouts
+        let exit = &mut ctx.accounts.exit_event;
+        exit.company_id = company.company_id;
+        exit.proceeds = proceeds;
+        for (i, declared) in classes.iter().enumerate() {
+            exit.class_ids[i] = declared.class_id;
+            exit.payout_per_share[i] = payouts[i];
+        }
+        exit.class_count = classes.len() as u8;
+        exit.declared_at = Clock::get()?.unix_timestamp;
+        exit.quote_mint = company.quote_mint;
+        exit.bump = ctx.bumps.exit_event;
+        exit.unclaimed = e_proceeds;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            admin,
+            ctx.accounts.system_program.to_account_info(),
+            class_accounts.as_slice(),
+        );
+        grants.allow(company.cusd.0, company.company_admin)?;
+        grants.allow_auditor(company, company.cusd.0)?;
+        grants.allow(e_proceeds.0, company.company_admin)?;
+        Ok(())
+    }
+
+    /// Holder: claim the exit payout for a position into the vault. The position is closed.
+    /// Payouts are capped by the unclaimed escrow, so claims never exceed the proceeds.
+    pub fn claim_exit_proceeds<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimExitProceeds<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .platform_config
+            .require_not_paused(PauseScope::Primary)?;
+        let position = &ctx.accounts.position;
+        require_keys_eq!(
+            ctx.accounts.owner_vault.mint,
+            ctx.accounts.exit_event.quote_mint,
+            DonatradeError::QuoteMintMismatch
+        );
+        let payout_per_share = ctx
+            .accounts
+            .exit_event
+            .payout_for(position.class_id)
+            .ok_or(DonatradeError::InvalidShareClass)?;
+
+        let inco_program = ctx.accounts.inco_lightning_program.to_account_info();
+        let owner = ctx.accounts.owner.to_account_info();
+
+        let e_rate = as_euint128(op_ctx(&inco_program, &owner), payout_per_share as u128)?;
+        let e_payout = e_mul(
+            op_ctx(&inco_program, &owner),
+            position.encrypted_shares,
+            e_rate,
+            0,
+        )?;
+        let exit = &mut ctx.accounts.exit_event;
+        let covered = e_ge(op_ctx(&inco_program, &owner), exit.unclaimed, e_payout, 0)?;
+        let e_paid = e_select(
+            op_ctx(&inco_program, &owner),
+            covered,
+            e_payout,
+            exit.unclaimed,
+            0,
+        )?;
+        exit.unclaimed = e_sub(op_ctx(&inco_program, &owner), exit.unclaimed, e_paid, 0)?;
+        ctx.accounts.owner_vault.cusd = e_add(
+            op_ctx(&inco_program, &owner),
+            ctx.accounts.owner_vault.cusd,
+            e_paid,
+            0,
+        )?;
+
+        let mut grants = HandleGrants::new(
+            inco_program,
+            owner,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        grants.allow(ctx.accounts.owner_vault.cusd.0, ctx.accounts.owner.key())?;
+        Ok(())
+    }
+}
+
+/// Superseded account layouts, kept so `migrate_*` can decode old accounts.
+pub mod legacy {
+    use super::*;
+
+    /// An old layout of an account that upgrades into `T`.
+    pub trait LegacyLayout<T>: AnchorDeserialize + Into<T> {
+        /// Account length, discriminator included.
+        const LEN: usize;
+        /// Version byte the layout was written with; `None` before versioning.
+        const VERSION: Option<u8>;
+    }
+
+    /// Decoder for one legacy layout of `T`, see `upgrade`.
+    pub type LegacyDecoder<T> = fn(&[u8]) -> Option<Result<T>>;
+
+    /// Decodes `data` as layout `V` and upgrades it, if `data` is in that layout.
+    pub fn upgrade<V: LegacyLayout<T>, T>(data: &[u8]) -> Option<Result<T>> {
+        let matches =
+            data.len() == V::LEN && (V::VERSION.is_none() || data.get(8) == V::VERSION.as_ref());
+        matches.then(|| Ok(V::deserialize(&mut &data[8..])?.into()))
+    }
+
+    /// Layouts `migrate_*` accepts, per account type.
+    pub const INVESTOR_VAULT_LAYOUTS: &[LegacyDecoder<InvestorVault>] =
+        &[upgrade::<InvestorVaultV1, _>, upgrade::<InvestorVaultV2, _>];
+    pub const COMPANY_ACCOUNT_LAYOUTS: &[LegacyDecoder<CompanyAccount>] = &[
+        upgrade::<CompanyAccountV1, _>,
+        upgrade::<CompanyAccountV2, _>,
+    ];
+    pub const POSITION_ACCOUNT_LAYOUTS: &[LegacyDecoder<PositionAccount>] = &[
+        upgrade::<PositionAccountV1, _>,
+        upgrade::<PositionAccountV2, _>,
+    ];
+    pub const OFFER_ACCOUNT_LAYOUTS: &[LegacyDecoder<OfferAccount>] =
+        &[upgrade::<OfferAccountV1, _>, upgrade::<OfferAccountV2, _>];
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct InvestorVaultV1 {
+        pub owner: Pubkey,
+        pub cusd: Euint128,
+        pub bump: u8,
+    }
+
+    impl LegacyLayout<InvestorVault> for InvestorVaultV1 {
+        const LEN: usize = 8 + 32 + 16 + 1;
+        const VERSION: Option<u8> = None;
+    }
+
+    /// v2 vault before velocity tracking; `mint` had already taken the reserved space.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct InvestorVaultV2 {
+        pub version: u8,
+        pub owner: Pubkey,
+        pub cusd: Euint128,
+        pub bump: u8,
+        pub mint: Pubkey,
+    }
+
+    impl LegacyLayout<InvestorVault> for InvestorVaultV2 {
+        const LEN: usize = 8 + 1 + 32 + 16 + 1 + 32;
+        const VERSION: Option<u8> = Some(2);
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CompanyAccountV1 {
+        pub company_id: u64,
+        pub company_admin: Pubkey,
+        pub cusd: Euint128,
+        pub shares_available: Euint128,
+        pub price_per_share: u64,
+        pub active: bool,
+        pub bump: u8,
+    }
+
+    impl LegacyLayout<CompanyAccount> for CompanyAccountV1 {
+        const LEN: usize = 8 + 8 + 32 + 16 + 16 + 8 + 1 + 1;
+        const VERSION: Option<u8> = None;
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CompanyAccountV2 {
+        pub version: u8,
+        pub company_id: u64,
+        pub company_admin: Pubkey,
+        pub cusd: Euint128,
+        pub shares_available: Euint128,
+        pub price_per_share: u64,
+        pub legal_agreement_hash: [u8; 32],
+        pub legal_agreement_version: u32,
+        pub signers: [Pubkey; MAX_COMPANY_SIGNERS],
+        pub signer_count: u8,
+        pub threshold: u8,
+        pub signer_epoch: u32,
+        pub paused: bool,
+        pub active: bool,
+        pub bump: u8,
+        pub reserved: [u8; 32],
+    }
+
+    impl LegacyLayout<CompanyAccount> for CompanyAccountV2 {
+        const LEN: usize = 8
+            + 1
+            + 8
+            + 32
+            + 16
+            + 16
+            + 8
+            + 32
+            + 4
+            + 32 * MAX_COMPANY_SIGNERS
+            + 1
+            + 1
+            + 4
+            + 1
+            + 1
+            + 1
+            + 32;
+        const VERSION: Option<u8> = Some(2);
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct PositionAccountV1 {
+        pub owner: Pubkey,
+        pub company_id: u64,
+        pub encrypted_shares: Euint128,
+        pub bump: u8,
+    }
+
+    impl LegacyLayout<PositionAccount> for PositionAccountV1 {
+        const LEN: usize = 8 + 32 + 8 + 16 + 1;
+        const VERSION: Option<u8> = None;
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct PositionAccountV2 {
+        pub version: u8,
+        pub owner: Pubkey,
+        pub company_id: u64,
+        pub encrypted_shares: Euint128,
+        pub bump: u8,
+        pub reserved: [u8; 32],
+    }
+
+    impl LegacyLayout<PositionAccount> for PositionAccountV2 {
+        const LEN: usize = 8 + 1 + 32 + 8 + 16 + 1 + 32;
+        const VERSION: Option<u8> = Some(2);
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct OfferAccountV1 {
+        pub offer_id: u64,
+        pub seller: Pubkey,
+        pub company_id: u64,
+        pub share_amount: u64,
+        pub escrowed_shares: Euint128,
+        pub price_per_share: u64,
+        pub is_active: bool,
+        pub bump: u8,
+    }
+
+    impl LegacyLayout<OfferAccount> for OfferAccountV1 {
+        const LEN: usize = 8 + 8 + 32 + 8 + 8 + 16 + 8 + 1 + 1;
+        const VERSION: Option<u8> = None;
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct OfferAccountV2 {
+        pub version: u8,
+        pub offer_id: u64,
+        pub seller: Pubkey,
+        pub company_id: u64,
+        pub share_amount: u64,
+        pub escrowed_shares: Euint128,
+        pub price_per_share: u64,
+        pub is_active: bool,
+        pub bump: u8,
+        pub reserved: [u8; 32],
+    }
+
+    impl LegacyLayout<OfferAccount> for OfferAccountV2 {
+        const LEN: usize = 8 + 1 + 8 + 32 + 8 + 8 + 16 + 8 + 1 + 1 + 32;
+        const VERSION: Option<u8> = Some(2);
+    }
+
+    impl From<InvestorVaultV1> for InvestorVault {
+        fn from(v1: InvestorVaultV1) -> Self {
+            InvestorVault {
+                version: CURRENT_ACCOUNT_VERSION,
+                owner: v1.owner,
+                cusd: v1.cusd,
+                bump: v1.bump,
+                ..Default::default()
+            }
+        }
+    }
+
+    // Velocity counters start empty, with no per-vault override.
+    impl From<InvestorVaultV2> for InvestorVault {
+        fn from(v2: InvestorVaultV2) -> Self {
+            InvestorVault {
+                version: CURRENT_ACCOUNT_VERSION,
+                owner: v2.owner,
+                cusd: v2.cusd,
+                bump: v2.bump,
+                mint: v2.mint,
+                ..Default::default()
+            }
+        }
+    }
+
+    // New company fields start out empty: no agreement, no multisig, not paused.
+    impl From<CompanyAccountV1> for CompanyAccount {
+        fn from(v1: CompanyAccountV1) -> Self {
+            CompanyAccount {
+                version: CURRENT_ACCOUNT_VERSION,
+                company_id: v1.company_id,
+                company_admin: v1.company_admin,
+                cusd: v1.cusd,
+                shares_available: v1.shares_available,
+                price_per_share: v1.price_per_share,
+                active: v1.active,
+                bump: v1.bump,
+                ..Default::default()
+            }
+        }
+    }
+
+    // v3 fields start out empty: public pricing, no auditor, USDC, no velocity override.
+    impl From<CompanyAccountV2> for CompanyAccount {
+        fn from(v2: CompanyAccountV2) -> Self {
+            CompanyAccount {
+                version: CURRENT_ACCOUNT_VERSION,
+                company_id: v2.company_id,
+                company_admin: v2.company_admin,
+                cusd: v2.cusd,
+                shares_available: v2.shares_available,
+                price_per_share: v2.price_per_share,
+                legal_agreement_hash: v2.legal_agreement_hash,
+                legal_agreement_version: v2.legal_agreement_version,
+                signers: v2.signers,
+                signer_count: v2.signer_count,
+                threshold: v2.threshold,
+                signer_epoch: v2.signer_epoch,
+                paused: v2.paused,
+                active: v2.active,
+                bump: v2.bump,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl From<PositionAccountV1> for PositionAccount {
+        fn from(v1: PositionAccountV1) -> Self {
+            PositionAccount {
+                version: CURRENT_ACCOUNT_VERSION,
+                owner: v1.owner,
+                company_id: v1.company_id,
+                encrypted_shares: v1.encrypted_shares,
+                bump: v1.bump,
+                ..Default::default()
+            }
+        }
+    }
+
+    // Same size as v3 with a zero class byte; migrating only stamps the version.
+    impl From<PositionAccountV2> for PositionAccount {
+        fn from(v2: PositionAccountV2) -> Self {
+            PositionAccount {
+                version: CURRENT_ACCOUNT_VERSION,
+                owner: v2.owner,
+                company_id: v2.company_id,
+                encrypted_shares: v2.encrypted_shares,
+                bump: v2.bump,
+                class_id: COMMON_CLASS_ID,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl From<OfferAccountV1> for OfferAccount {
+        fn from(v1: OfferAccountV1) -> Self {
+            OfferAccount {
+                version: CURRENT_ACCOUNT_VERSION,
+                offer_id: v1.offer_id,
+                seller: v1.seller,
+                company_id: v1.company_id,
+                share_amount: v1.share_amount,
+                escrowed_shares: v1.escrowed_shares,
+                price_per_share: v1.price_per_share,
+                is_active: v1.is_active,
+                bump: v1.bump,
+                ..Default::default()
+            }
+        }
+    }
+
+    // Offers from before share classes are common stock, publicly priced and open to anyone.
+    impl From<OfferAccountV2> for OfferAccount {
+        fn from(v2: OfferAccountV2) -> Self {
+            OfferAccount {
+                version: CURRENT_ACCOUNT_VERSION,
+                offer_id: v2.offer_id,
+                seller: v2.seller,
+                company_id: v2.company_id,
+                share_amount: v2.share_amount,
+                escrowed_shares: v2.escrowed_shares,
+                price_per_share: v2.price_per_share,
+                is_active: v2.is_active,
+                bump: v2.bump,
+                reserved: v2.reserved,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+/// Vaults only settle against companies quoted in the same mint.
+fn require_quote_mint(company: &CompanyAccount, vault: &InvestorVault) -> Result<()> {
+    require_keys_eq!(
+        vault.mint,
+        company.quote_mint,
+        DonatradeError::QuoteMintMismatch
+    );
+    Ok(())
+}
+
+/// Counts one primary purchase against the buyer's vault and the issuing company's limits.
+fn record_primary_purchase(
+    config: &PlatformConfig,
+    vault: &mut InvestorVault,
+    company: &mut CompanyAccount,
+    now: i64,
+) -> Result<()> {
+    let vault_limits = config.velocity_limits_for(vault.velocity_override);
+    vault.purchases.record(
+        now,
+        1,
+        vault_limits.purchase_limit,
+        DonatradeError::PurchaseLimitExceeded,
+    )?;
+    let company_limits = config.velocity_limits_for(company.velocity_override);
+    company.primary_sales.record(
+        now,
+        1,
+        company_limits.purchase_limit,
+        DonatradeError::PurchaseLimitExceeded,
+    )
+}
+
+/// Decodes raw account data in any of the `layouts` and upgrades it to the current layout.
+fn upgrade_account_data<T: Discriminator>(
+    data: &[u8],
+    layouts: &[legacy::LegacyDecoder<T>],
+) -> Result<T> {
+    require!(
+        data.starts_with(T::DISCRIMINATOR),
+        anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+    );
+    if let Some(upgraded) = layouts.iter().find_map(|upgrade| upgrade(data)) {
+        return upgraded;
+    }
+    require!(
+        data.len() <= 8 || data[8] != CURRENT_ACCOUNT_VERSION,
+        DonatradeError::AlreadyMigrated
+    );
+    err!(DonatradeError::UnsupportedAccountVersion)
+}
+
+/// Upgrades an account from a legacy layout to the current one in place.
+/// Checks owner and discriminator, tops up rent from `payer`, reallocs and rewrites the data.
+fn migrate_account<'info, T>(
+    account: &UncheckedAccount<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    layouts: &[legacy::LegacyDecoder<T>],
+    new_len: usize,
+) -> Result<()>
+where
+    T: AccountSerialize + Discriminator,
+{
+    let info = account.to_account_info();
+    require_keys_eq!(
+        *info.owner,
+        crate::ID,
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+
+    let upgraded: T = upgrade_account_data(&info.try_borrow_data()?, layouts)?;
+
+    let rent_due = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(info.lamports());
+    if rent_due > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+    info.realloc(new_len, true)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    upgraded.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+/// Enforces M-of-N approval for a sensitive company action.
+/// A no-op while the company has no multisig configured.
+fn authorize_company_action(
+    company: &CompanyAccount,
+    proposal: Option<&mut Account<CompanyProposal>>,
+    action: &CompanyAction,
+) -> Result<()> {
+    if !company.multisig_enabled() {
+        return Ok(());
+    }
+    let proposal = proposal.ok_or(DonatradeError::ProposalRequired)?;
+    require!(
+        proposal.company_id == company.company_id && proposal.action == *action,
+        DonatradeError::ProposalMismatch
+    );
+    require!(
+        proposal.signer_epoch == company.signer_epoch,
+        DonatradeError::StaleProposal
+    );
+    require!(!proposal.executed, DonatradeError::ProposalExecuted);
+    require!(
+        proposal.approval_count >= company.threshold,
+        DonatradeError::InsufficientApprovals
+    );
+    proposal.executed = true;
+    Ok(())
+}
+
+/// Liquidation waterfall over plaintext cap-table totals. Returns the payout per share of each tranche.
+/// Preferences are paid by descending seniority, pro-rata by preference amount within a seniority
+/// level when proceeds run short; whatever is left goes to common and participating preferred
+/// pro-rata on an as-converted basis. Rounding dust stays undistributed.
+pub fn exit_waterfall(proceeds: u64, tranches: &[Tranche]) -> Vec<u64> {
+    let mut remaining = proceeds as u128;
+    let mut payouts = vec![0u64; tranches.len()];
+
+    // 1. Liquidation preferences, most senior first
+    let mut levels: Vec<u8> = tranches
+        .iter()
+        .filter(|t| t.preference_per_share > 0)
+        .map(|t| t.seniority)
+        .collect();
+    levels.sort_unstable_by(|a, b| b.cmp(a));
+    levels.dedup();
+    for level in levels {
+        let owed: u128 = tranches
+            .iter()
+            .filter(|t| t.seniority == level)
+            .map(|t| t.outstanding as u128 * t.preference_per_share as u128)
+            .sum();
+        if owed == 0 {
+            continue;
+        }
+        let paid_total = owed.min(remaining);
+        for (i, t) in tranches.iter().enumerate() {
+            if t.seniority != level || t.preference_per_share == 0 {
+                continue;
+            }
+            let per_share = t.preference_per_share as u128 * paid_total / owed;
+            payouts[i] = per_share as u64;
+            remaining -= per_share * t.outstanding as u128;
+        }
+    }
+
+    // 2. Residual to common and participating preferred, as converted
+    let units: u128 = tranches
+        .iter()
+        .filter(|t| t.participating)
+        .map(|t| t.outstanding as u128 * t.as_converted as u128)
+        .sum();
+    if let Some(per_unit) = remaining.checked_div(units) {
+        for (i, t) in tranches.iter().enumerate() {
+            if t.participating {
+                payouts[i] += (per_unit * t.as_converted as u128) as u64;
+            }
+        }
+    }
+    payouts
+}
+
+/// Position seed for a share class. Common stock uses no extra seed so its
+/// positions keep the addresses they had before share classes existed.
+pub fn class_seed(class_id: &u8) -> &[u8] {
+    if *class_id == COMMON_CLASS_ID {
+        &[]
+    } else {
+        std::slice::from_ref(class_id)
+    }
+}
+
+/// Resolves the `ShareClass` account for a non-common class.
+fn share_class_for<'a, 'info>(
+    company: &CompanyAccount,
+    class_id: u8,
+    share_class: Option<&'a mut Account<'info, ShareClass>>,
+) -> Result<&'a mut Account<'info, ShareClass>> {
+    let class = share_class.ok_or(DonatradeError::ShareClassRequired)?;
+    require!(
+        class.company_id == company.company_id && class.class_id == class_id,
+        DonatradeError::InvalidShareClass
+    );
+    Ok(class)
+}
+
+/// Reads one of a company's `ShareClass` accounts passed in the remaining accounts.
+fn load_share_class(company: &CompanyAccount, info: &AccountInfo) -> Result<ShareClass> {
+    require_keys_eq!(
+        *info.owner,
+        crate::ID,
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+    let class = ShareClass::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require!(
+        class.company_id == company.company_id,
+        DonatradeError::InvalidShareClass
+    );
+    Ok(class)
+}
+
+/// Fails unless shares of `class_id` may change hands between holders.
+/// Common stock is frozen while a rights period is open, so holdings cannot be
+/// moved to another wallet and snapshotted twice.
+fn require_transferable(
+    company: &CompanyAccount,
+    class_id: u8,
+    share_class: Option<&Account<ShareClass>>,
+) -> Result<()> {
+    if class_id == COMMON_CLASS_ID {
+        require!(
+            !company.rights_period.is_open(Clock::get()?.unix_timestamp),
+            DonatradeError::RightsTransferFrozen
+        );
+        return Ok(());
+    }
+    let class = share_class.ok_or(DonatradeError::ShareClassRequired)?;
+    require!(
+        class.company_id == company.company_id && class.class_id == class_id,
+        DonatradeError::InvalidShareClass
+    );
+    require!(class.transferable, DonatradeError::ClassNotTransferable);
+    Ok(())
+}
+
+/// Grants decryption rights on freshly written handles via Inco `allow`.
+/// Each grant consumes the next (allowance PDA, allowed address) pair from the remaining accounts,
+/// in the order the handler issues them. Clients simulate first to learn the new handles.
+struct HandleGrants<'a, 'info> {
+    inco_program: AccountInfo<'info>,
+    signer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    remaining: std::slice::Iter<'a, AccountInfo<'info>>,
+}
+
+impl<'a, 'info> HandleGrants<'a, 'info> {
+    fn new(
+        inco_program: AccountInfo<'info>,
+        signer: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+        remaining_accounts: &'a [AccountInfo<'info>],
+    ) -> Self {
+        Self {
+            inco_program,
+            signer,
+            system_program,
+            remaining: remaining_accounts.iter(),
+        }
+    }
+
+    fn allow(&mut self, handle: u128, allowed: Pubkey) -> Result<()> {
+        let allowance_account = self
+            .remaining
+            .next()
+            .ok_or(DonatradeError::MissingAllowanceAccounts)?;
+        let allowed_address = self
+            .remaining
+            .next()
+            .ok_or(DonatradeError::MissingAllowanceAccounts)?;
+        require_keys_eq!(
+            allowed_address.key(),
+            allowed,
+            DonatradeError::AllowedAddressMismatch
+        );
+
+        allow(
+            CpiContext::new(
+                self.inco_program.clone(),
+                Allow {
+                    allowance_account: allowance_account.clone(),
+                    signer: self.signer.clone(),
+                    allowed_address: allowed_address.clone(),
+                    system_program: self.system_program.clone(),
+                },
+            ),
+            handle,
+            true,
+            allowed,
+        )
+    }
+
+    /// Grants the company's auditor access, if one is set.
+    fn allow_auditor(&mut self, company: &CompanyAccount, handle: u128) -> Result<()> {
+        match company.auditor() {
+            Some(auditor) => self.allow(handle, auditor),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the encrypted unit price: the stored handle in private mode,
+/// otherwise the plaintext price lifted with `as_euint128`.
+fn price_handle<'info>(
+    inco_program: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    private_pricing: bool,
+    encrypted_price: Euint128,
+    price_per_share: u64,
+) -> Result<Euint128> {
+    if private_pricing {
+        return Ok(encrypted_price);
+    }
+    as_euint128(
+        CpiContext::new(
+            inco_program.clone(),
+            Operation {
+                signer: signer.clone(),
+            },
+        ),
+        price_per_share as u128,
+    )
+}
+
+/// Grants (`allowed = true`) or revokes decryption of `handle` for `allowed_address`.
+/// The flag is Inco's allowance value: `false` rewrites the allowance PDA as not allowed
+/// (covered by tests/allowance.ts).
+fn set_allowance<'info>(
+    inco_program: &AccountInfo<'info>,
+    signer: &Signer<'info>,
+    allowance_account: &UncheckedAccount<'info>,
+    allowed_address: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    handle: u128,
+    allowed: bool,
+) -> Result<()> {
+    allow(
+        CpiContext::new(
+            inco_program.clone(),
+            Allow {
+                allowance_account: allowance_account.to_account_info(),
+                signer: signer.to_account_info(),
+                allowed_address: allowed_address.to_account_info(),
+                system_program: system_program.to_account_info(),
+            },
+        ),
+        handle,
+        allowed,
+        allowed_address.key(),
+    )
+}
+
+/// Computes an encrypted `balance == 0` and lets the owner decrypt the result.
+fn encrypted_is_zero<'info>(
+    inco_program: &AccountInfo<'info>,
+    owner: &Signer<'info>,
+    allowance_account: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    balance: Euint128,
+) -> Result<Ebool> {
+    let signer = owner.to_account_info();
+    let e_zero = as_euint128(
+        CpiContext::new(
+            inco_program.clone(),
+            Operation {
+                signer: signer.clone(),
+            },
+        ),
+        0,
+    )?;
+    let is_zero = e_eq(
+        CpiContext::new(
+            inco_program.clone(),
+            Operation {
+                signer: signer.clone(),
+            },
+        ),
+        balance,
+        e_zero,
+        0,
+    )?;
+
+    allow(
+        CpiContext::new(
+            inco_program.clone(),
+            Allow {
+                allowance_account: allowance_account.to_account_info(),
+                signer: signer.clone(),
+                allowed_address: signer,
+                system_program: system_program.to_account_info(),
+            },
+        ),
+        is_zero.0,
+        true,
+        owner.key(),
+    )?;
+    Ok(is_zero)
+}
+
+/// Checks the Inco attestations (Ed25519 instructions earlier in this transaction)
+/// that each `(handle, plaintext)` pair decrypts as stated.
+fn verify_decryption<'info>(
+    inco_program: &AccountInfo<'info>,
+    instructions: &UncheckedAccount<'info>,
+    signer: &Signer<'info>,
+    decryptions: &[(u128, u128)],
+) -> Result<()> {
+    is_validsignature(
+        CpiContext::new(
+            inco_program.clone(),
+            VerifySignature {
+                instructions: instructions.to_account_info(),
+                signer: signer.to_account_info(),
+            },
+        ),
+        decryptions.len() as u8,
+        Some(
+            decryptions
+                .iter()
+                .map(|(handle, _)| handle.to_le_bytes().to_vec())
+                .collect(),
+        ),
+        Some(
+            decryptions
+                .iter()
+                .map(|(_, plaintext)| plaintext.to_le_bytes().to_vec())
+                .collect(),
+        ),
+    )?;
+    Ok(())
+}
+
+/// `floor(x * num / den)` for an encrypted `x` below 2^64 and public `num`, `den`.
+/// Inco has no encrypted division, so the fractional part of `num / den` is applied as a
+/// 64-bit fixed point (`e_mul`, then `e_shr`) and the one unit truncation can lose is added
+/// back when `(q + 1) * den` still fits under `x * num`.
+fn e_mul_div<'info>(
+    inco_program: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    x: Euint128,
+    num: u64,
+    den: u64,
+) -> Result<Euint128> {
+    let whole = num.checked_div(den).ok_or(DonatradeError::Overflow)?;
+    let part = num % den;
+    let e_whole = as_euint128(op_ctx(inco_program, signer), whole as u128)?;
+    let e_whole_part = e_mul(op_ctx(inco_program, signer), x, e_whole, 0)?;
+    if part == 0 {
+        return Ok(e_whole_part);
+    }
+
+    let fixed = ((part as u128) << 64) / den as u128;
+    let e_fixed = as_euint128(op_ctx(inco_program, signer), fixed)?;
+    let e_shift = as_euint128(op_ctx(inco_program, signer), 64)?;
+    let e_scaled = e_mul(op_ctx(inco_program, signer), x, e_fixed, 0)?;
+    let e_low = e_shr(op_ctx(inco_program, signer), e_scaled, e_shift, 0)?;
+
+    let e_one = as_euint128(op_ctx(inco_program, signer), 1)?;
+    let e_high = e_add(op_ctx(inco_program, signer), e_low, e_one, 0)?;
+    let e_part = as_euint128(op_ctx(inco_program, signer), part as u128)?;
+    let e_den = as_euint128(op_ctx(inco_program, signer), den as u128)?;
+    let e_exact = e_mul(op_ctx(inco_program, signer), x, e_part, 0)?;
+    let e_high_scaled = e_mul(op_ctx(inco_program, signer), e_high, e_den, 0)?;
+    let high_fits = e_ge(op_ctx(inco_program, signer), e_exact, e_high_scaled, 0)?;
+    let e_frac = e_select(op_ctx(inco_program, signer), high_fits, e_high, e_low, 0)?;
+    e_add(op_ctx(inco_program, signer), e_whole_part, e_frac, 0)
+}
+
+/// Moves an outstanding share count by `shares` issued to holders (`issued`) or taken back
+/// from them. Counts still at the zero handle predate tracking and are left untracked.
+fn track_outstanding<'info>(
+    inco_program: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    outstanding: &mut Euint128,
+    shares: Euint128,
+    issued: bool,
+) -> Result<()> {
+    if outstanding.0 == 0 {
+        return Ok(());
+    }
+    *outstanding = if issued {
+        e_add(op_ctx(inco_program, signer), *outstanding, shares, 0)?
+    } else {
+        e_sub(op_ctx(inco_program, signer), *outstanding, shares, 0)?
+    };
+    Ok(())
+}
+
+/// Shorthand for an Inco operation CPI context signed by `signer`.
+fn op_ctx<'info>(
+    inco_program: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+) -> CpiContext<'static, 'static, 'static, 'info, Operation<'info>> {
+    CpiContext::new(
+        inco_program.clone(),
+        Operation {
+            signer: signer.clone(),
+        },
+    )
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalVault<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(init, payer = admin, space = 8 + GlobalProgramVault::INIT_SPACE, seeds = [b"vault_authority"], bump)]
+    pub global_vault: Account<'info, GlobalProgramVault>,
+    pub usdc_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterQuoteMint<'info> {
+    #[account(mut, address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"vault_authority"], bump = global_vault.bump)]
+    pub global_vault: Account<'info, GlobalProgramVault>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint, token::authority = global_vault, token::token_program = token_program)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(init, payer = admin, space = 8 + QuoteMint::INIT_SPACE, seeds = [b"quote_mint", mint.key().as_ref()], bump)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetQuoteMintActive<'info> {
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"quote_mint", quote_mint.mint.as_ref()], bump = quote_mint.bump)]
+    pub quote_mint: Account<'info, QuoteMint>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePlatformConfig<'info> {
+    /// Must be the program's upgrade authority
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(init, payer = admin, space = 8 + PlatformConfig::INIT_SPACE, seeds = [b"platform_config"], bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::DonatradeProgram>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ DonatradeError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseGuardian<'info> {
+    #[account(mut, seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetWithdrawalPolicy<'info> {
+    #[account(mut, seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVelocityLimits<'info> {
+    #[account(mut, seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultVelocityOverride<'info> {
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"vault", investor_vault.owner.as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+}
+
+#[derive(Accounts)]
+pub struct SetCompanyVelocityOverride<'info> {
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePause<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCompanyPause<'info> {
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(company_id: u64)]
+pub struct ActivateCompany<'info> {
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub platform_admin: Signer<'info>,
+    #[account(
+        init,
+        payer = platform_admin,
+        space = 8 + CompanyAccount::INIT_SPACE,
+        seeds = [b"company", company_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Quote currency for the company's prices and balance (default = USDC)
+    #[account(seeds = [b"quote_mint", quote_mint.mint.as_ref()], bump = quote_mint.bump)]
+    pub quote_mint: Option<Account<'info, QuoteMint>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"quote_mint", mint.key().as_ref()], bump = quote_mint.bump, has_one = mint, has_one = vault_token_account)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    #[account(init_if_needed, payer = investor, space = 8 + InvestorVault::INIT_SPACE, seeds = [b"vault", investor.key().as_ref(), mint_seed(&quote_mint.balance_key)], bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub investor_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"vault", investor.key().as_ref(), mint_seed(&investor_vault.mint)], bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(seeds = [b"vault_authority"], bump)]
+    pub global_vault: Account<'info, GlobalProgramVault>,
+    #[account(seeds = [b"quote_mint", mint.key().as_ref()], bump = quote_mint.bump, has_one = mint, has_one = vault_token_account)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub investor_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(e_shares: Euint128, class_id: u8)]
+pub struct BuyShares<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"vault", investor.key().as_ref(), mint_seed(&investor_vault.mint)], bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(mut)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Required unless buying common stock
+    #[account(mut, seeds = [b"share_class", company_account.company_id.to_le_bytes().as_ref(), &[class_id]], bump = share_class.bump)]
+    pub share_class: Option<Account<'info, ShareClass>>,
+    #[account(init_if_needed, payer = investor, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), class_seed(&class_id), investor.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SellShares<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"vault", investor.key().as_ref(), mint_seed(&investor_vault.mint)], bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(mut)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ShareVaultBalance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(has_one = owner, seeds = [b"vault", owner.key().as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    /// CHECK: The address to grant or revoke access for
+    pub allowed_address: UncheckedAccount<'info>,
+    /// CHECK: Validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SharePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(has_one = owner, seeds = [b"position", position.company_id.to_le_bytes().as_ref(), class_seed(&position.class_id), owner.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    /// CHECK: The address to grant or revoke access for
+    pub allowed_address: UncheckedAccount<'info>,
+    /// CHECK: Validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ShareCompanyBalance<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// CHECK: The address to grant or revoke access for
+    pub allowed_address: UncheckedAccount<'info>,
+    /// CHECK: Validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOffering<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPrivatePrice<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ShareOfferPrice<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(has_one = seller, seeds = [b"offer", seller.key().as_ref(), offer_account.offer_id.to_le_bytes().as_ref()], bump = offer_account.bump)]
+    pub offer_account: Account<'info, OfferAccount>,
+    /// CHECK: The address to grant access to
+    pub allowed_address: UncheckedAccount<'info>,
+    /// CHECK: Validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ProposeCompanyAction<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + CompanyProposal::INIT_SPACE,
+        seeds = [b"proposal", company_account.company_id.to_le_bytes().as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, CompanyProposal>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveCompanyAction<'info> {
+    pub approver: Signer<'info>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"proposal", company_account.company_id.to_le_bytes().as_ref(), proposal.proposal_id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, CompanyProposal>,
+}
+
+#[derive(Accounts)]
+pub struct SetCompanySigners<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct RotateCompanyAdmin<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct SetCompanyAuditor<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub platform_admin: Signer<'info>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct RecoverCompanyAdmin<'info> {
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub platform_admin: Signer<'info>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLegalAgreement<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptLegalAgreement<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = 8 + AgreementAcceptance::INIT_SPACE,
+        seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCompanyFunds<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(seeds = [b"vault_authority"], bump)]
+    pub global_vault: Account<'info, GlobalProgramVault>,
+    #[account(seeds = [b"quote_mint", mint.key().as_ref()], bump = quote_mint.bump, has_one = mint, has_one = vault_token_account)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(offer_id: u64)]
+pub struct CreateOffer<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), class_seed(&seller_position.class_id), seller.key().as_ref()], bump = seller_position.bump)]
+    pub seller_position: Account<'info, PositionAccount>,
+    /// Required unless offering common stock
+    #[account(seeds = [b"share_class", company_account.company_id.to_le_bytes().as_ref(), &[seller_position.class_id]], bump = share_class.bump)]
+    pub share_class: Option<Account<'info, ShareClass>>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + OfferAccount::INIT_SPACE,
+        seeds = [b"offer", seller.key().as_ref(), offer_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub offer_account: Account<'info, OfferAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTrade<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"offer", offer_account.seller.as_ref(), offer_account.offer_id.to_le_bytes().as_ref()], bump = offer_account.bump)]
+    pub offer_account: Account<'info, OfferAccount>,
+    #[account(mut, seeds = [b"vault", buyer.key().as_ref(), mint_seed(&buyer_vault.mint)], bump = buyer_vault.bump)]
+    pub buyer_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"vault", offer_account.seller.as_ref(), mint_seed(&seller_vault.mint)], bump = seller_vault.bump)]
+    pub seller_vault: Account<'info, InvestorVault>,
+    /// The company for which shares are being traded
+    #[account(seeds = [b"company", offer_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Buyer's position account for receiving shares
+    #[account(init_if_needed, payer = buyer, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), class_seed(&offer_account.class_id), buyer.key().as_ref()], bump)]
+    pub buyer_position: Account<'info, PositionAccount>,
+    /// Required unless trading common stock
+    #[account(seeds = [b"share_class", company_account.company_id.to_le_bytes().as_ref(), &[offer_account.class_id]], bump = share_class.bump)]
+    pub share_class: Option<Account<'info, ShareClass>>,
+    /// Buyer's acceptance of the company's current legal agreement
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), buyer.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Owner, discriminator and layout are verified in the handler
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestClosePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(has_one = owner, seeds = [b"position", position.company_id.to_le_bytes().as_ref(), class_seed(&position.class_id), owner.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(init_if_needed, payer = owner, space = 8 + CloseRequest::INIT_SPACE, seeds = [b"close", position.key().as_ref()], bump)]
+    pub close_request: Account<'info, CloseRequest>,
+    /// CHECK: Allowance PDA for the zero-check handle, validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, close = owner, has_one = owner, seeds = [b"position", position.company_id.to_le_bytes().as_ref(), class_seed(&position.class_id), owner.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(mut, close = owner, seeds = [b"close", position.key().as_ref()], bump = close_request.bump)]
+    pub close_request: Account<'info, CloseRequest>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestCloseVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(has_one = owner, seeds = [b"vault", owner.key().as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = owner, space = 8 + CloseRequest::INIT_SPACE, seeds = [b"close", investor_vault.key().as_ref()], bump)]
+    pub close_request: Account<'info, CloseRequest>,
+    /// CHECK: Allowance PDA for the zero-check handle, validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, close = owner, has_one = owner, seeds = [b"vault", owner.key().as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(mut, close = owner, seeds = [b"close", investor_vault.key().as_ref()], bump = close_request.bump)]
+    pub close_request: Account<'info, CloseRequest>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct CreateAuction<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(
+        init,
+        payer = company_admin,
+        space = 8 + Auction::INIT_SPACE,
+        seeds = [b"auction", company_account.company_id.to_le_bytes().as_ref(), auction_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitBid<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"vault", investor.key().as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"auction", company_account.company_id.to_le_bytes().as_ref(), auction.auction_id.to_le_bytes().as_ref()], bump = auction.bump)]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        init,
+        payer = investor,
+        space = 8 + AuctionBid::INIT_SPACE,
+        seeds = [b"bid", auction.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub bid: Account<'info, AuctionBid>,
+    #[account(init_if_needed, payer = investor, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseAuction<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"auction", company_account.company_id.to_le_bytes().as_ref(), auction.auction_id.to_le_bytes().as_ref()], bump = auction.bump)]
+    pub auction: Account<'info, Auction>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeAuctionPrice<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"auction", company_account.company_id.to_le_bytes().as_ref(), auction.auction_id.to_le_bytes().as_ref()], bump = auction.bump)]
+    pub auction: Account<'info, Auction>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signatures
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBid<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"auction", company_account.company_id.to_le_bytes().as_ref(), auction.auction_id.to_le_bytes().as_ref()], bump = auction.bump)]
+    pub auction: Account<'info, Auction>,
+    /// Settled bids are closed and their rent returned to the bidder
+    #[account(mut, close = bidder, has_one = bidder, seeds = [b"bid", auction.key().as_ref(), bidder.key().as_ref()], bump = bid.bump)]
+    pub bid: Account<'info, AuctionBid>,
+    /// CHECK: Bid owner, checked against the bid via has_one
+    #[account(mut)]
+    pub bidder: UncheckedAccount<'info>,
+    /// Recreated if the bidder closed it while the bid was open
+    #[account(init_if_needed, payer = payer, space = 8 + InvestorVault::INIT_SPACE, seeds = [b"vault", bidder.key().as_ref(), mint_seed(&company_account.quote_mint)], bump)]
+    pub bidder_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = payer, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), bidder.key().as_ref()], bump)]
+    pub bidder_position: Account<'info, PositionAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DutchAuctionAdmin<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct RequestDutchExhaustionCheck<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// CHECK: Allowance PDA for the check handle, validated by Inco program
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EndDutchAuction<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeOrderBook<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(zero)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, address = company_account.order_book @ DonatradeError::OrderBookMismatch)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(mut, seeds = [b"vault", trader.key().as_ref(), mint_seed(&trader_vault.mint)], bump = trader_vault.bump)]
+    pub trader_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = trader, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), trader.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    /// Required for bids: the trader's acceptance of the current legal agreement
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), trader.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Option<Account<'info, AgreementAcceptance>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, address = company_account.order_book @ DonatradeError::OrderBookMismatch)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(mut, seeds = [b"vault", owner.key().as_ref(), mint_seed(&owner_vault.mint)], bump = owner_vault.bump)]
+    pub owner_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), owner.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, address = company_account.order_book @ DonatradeError::OrderBookMismatch)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    /// Vaults and position are matched against the order owners in the handler
+    #[account(mut, seeds = [b"vault", buyer_vault.owner.as_ref(), mint_seed(&buyer_vault.mint)], bump = buyer_vault.bump)]
+    pub buyer_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), buyer_position.owner.as_ref()], bump = buyer_position.bump)]
+    pub buyer_position: Account<'info, PositionAccount>,
+    #[account(mut, seeds = [b"vault", seller_vault.owner.as_ref(), mint_seed(&seller_vault.mint)], bump = seller_vault.bump)]
+    pub seller_vault: Account<'info, InvestorVault>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EvictOrder<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, address = company_account.order_book @ DonatradeError::OrderBookMismatch)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    /// CHECK: Owner of the head order, checked against the book in the handler
+    pub owner: UncheckedAccount<'info>,
+    #[account(init_if_needed, payer = cranker, space = 8 + InvestorVault::INIT_SPACE, seeds = [b"vault", owner.key().as_ref(), mint_seed(&company_account.quote_mint)], bump)]
+    pub owner_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = cranker, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), owner.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PopFilledOrder<'info> {
+    pub cranker: Signer<'info>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, address = company_account.order_book @ DonatradeError::OrderBookMismatch)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(swap_id: u64)]
+pub struct CreateSwapOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", give_company.company_id.to_le_bytes().as_ref()], bump = give_company.bump)]
+    pub give_company: Account<'info, CompanyAccount>,
+    #[account(seeds = [b"company", want_company.company_id.to_le_bytes().as_ref()], bump = want_company.bump)]
+    pub want_company: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"position", give_company.company_id.to_le_bytes().as_ref(), maker.key().as_ref()], bump = maker_give_position.bump)]
+    pub maker_give_position: Account<'info, PositionAccount>,
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + SwapOffer::INIT_SPACE,
+        seeds = [b"swap", maker.key().as_ref(), swap_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub swap_offer: Account<'info, SwapOffer>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ShareSwapTerms<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(has_one = maker, seeds = [b"swap", maker.key().as_ref(), swap_offer.swap_id.to_le_bytes().as_ref()], bump = swap_offer.bump)]
+    pub swap_offer: Account<'info, SwapOffer>,
+    /// CHECK: The address to grant access to
+    pub allowed_address: UncheckedAccount<'info>,
+    /// CHECK: Allowance PDA for the escrowed shares, validated by Inco program
+    #[account(mut)]
+    pub give_allowance_account: UncheckedAccount<'info>,
+    /// CHECK: Allowance PDA for the requested shares, validated by Inco program
+    #[account(mut)]
+    pub want_allowance_account: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TakeSwapOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, close = maker, has_one = maker, seeds = [b"swap", maker.key().as_ref(), swap_offer.swap_id.to_le_bytes().as_ref()], bump = swap_offer.bump)]
+    pub swap_offer: Account<'info, SwapOffer>,
+    /// CHECK: Swap maker, checked against the offer via has_one
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+    #[account(seeds = [b"company", swap_offer.give_company_id.to_le_bytes().as_ref()], bump = give_company.bump)]
+    pub give_company: Account<'info, CompanyAccount>,
+    #[account(seeds = [b"company", swap_offer.want_company_id.to_le_bytes().as_ref()], bump = want_company.bump)]
+    pub want_company: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"position", swap_offer.want_company_id.to_le_bytes().as_ref(), taker.key().as_ref()], bump = taker_want_position.bump)]
+    pub taker_want_position: Account<'info, PositionAccount>,
+    #[account(init_if_needed, payer = taker, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", swap_offer.want_company_id.to_le_bytes().as_ref(), maker.key().as_ref()], bump)]
+    pub maker_want_position: Account<'info, PositionAccount>,
+    #[account(init_if_needed, payer = taker, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", swap_offer.give_company_id.to_le_bytes().as_ref(), taker.key().as_ref()], bump)]
+    pub taker_give_position: Account<'info, PositionAccount>,
+    /// Gets the escrow back if the taker cannot cover the want leg
+    #[account(init_if_needed, payer = taker, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", swap_offer.give_company_id.to_le_bytes().as_ref(), maker.key().as_ref()], bump)]
+    pub maker_give_position: Account<'info, PositionAccount>,
+    /// Taker's acceptance of the give company's current legal agreement
+    #[account(seeds = [b"agreement", swap_offer.give_company_id.to_le_bytes().as_ref(), taker.key().as_ref()], bump = taker_acceptance.bump)]
+    pub taker_acceptance: Account<'info, AgreementAcceptance>,
+    /// Maker's acceptance of the want company's current legal agreement
+    #[account(seeds = [b"agreement", swap_offer.want_company_id.to_le_bytes().as_ref(), maker.key().as_ref()], bump = maker_acceptance.bump)]
+    pub maker_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSwapOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut, close = maker, has_one = maker, seeds = [b"swap", maker.key().as_ref(), swap_offer.swap_id.to_le_bytes().as_ref()], bump = swap_offer.bump)]
+    pub swap_offer: Account<'info, SwapOffer>,
+    #[account(seeds = [b"company", swap_offer.give_company_id.to_le_bytes().as_ref()], bump = give_company.bump)]
+    pub give_company: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"position", swap_offer.give_company_id.to_le_bytes().as_ref(), maker.key().as_ref()], bump = maker_give_position.bump)]
+    pub maker_give_position: Account<'info, PositionAccount>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct CreateSafeRound<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(
+        init,
+        payer = company_admin,
+        space = 8 + SafeRound::INIT_SPACE,
+        seeds = [b"safe_round", company_account.company_id.to_le_bytes().as_ref(), round_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub safe_round: Account<'info, SafeRound>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundSafe<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"vault", investor.key().as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(seeds = [b"safe_round", company_account.company_id.to_le_bytes().as_ref(), safe_round.round_id.to_le_bytes().as_ref()], bump = safe_round.bump)]
+    pub safe_round: Account<'info, SafeRound>,
+    #[account(init_if_needed, payer = investor, space = 8 + Safe::INIT_SPACE, seeds = [b"safe", safe_round.key().as_ref(), investor.key().as_ref()], bump)]
+    pub safe: Account<'info, Safe>,
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PriceSafeRound<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"safe_round", company_account.company_id.to_le_bytes().as_ref(), safe_round.round_id.to_le_bytes().as_ref()], bump = safe_round.bump)]
+    pub safe_round: Account<'info, SafeRound>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct ConvertSafe<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(seeds = [b"safe_round", company_account.company_id.to_le_bytes().as_ref(), safe_round.round_id.to_le_bytes().as_ref()], bump = safe_round.bump)]
+    pub safe_round: Account<'info, SafeRound>,
+    #[account(mut, close = investor, has_one = investor, seeds = [b"safe", safe_round.key().as_ref(), investor.key().as_ref()], bump = safe.bump)]
+    pub safe: Account<'info, Safe>,
+    /// CHECK: SAFE holder, checked against the SAFE via has_one
+    #[account(mut)]
+    pub investor: UncheckedAccount<'info>,
+    #[account(init_if_needed, payer = payer, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    /// Receives the principal the conversion does not cover
+    #[account(init_if_needed, payer = payer, space = 8 + InvestorVault::INIT_SPACE, seeds = [b"vault", investor.key().as_ref(), mint_seed(&company_account.quote_mint)], bump)]
+    pub investor_vault: Account<'info, InvestorVault>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundOptionPool<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(init_if_needed, payer = company_admin, space = 8 + OptionPool::INIT_SPACE, seeds = [b"option_pool", company_account.company_id.to_le_bytes().as_ref()], bump)]
+    pub option_pool: Account<'info, OptionPool>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(grant_id: u64)]
+pub struct IssueOptionGrant<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"option_pool", company_account.company_id.to_le_bytes().as_ref()], bump = option_pool.bump)]
+    pub option_pool: Account<'info, OptionPool>,
+    /// CHECK: Employee receiving the grant
+    pub employee: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = company_admin,
+        space = 8 + OptionGrant::INIT_SPACE,
+        seeds = [b"option_grant", company_account.company_id.to_le_bytes().as_ref(), employee.key().as_ref(), grant_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub option_grant: Account<'info, OptionGrant>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct TerminateOptionGrant<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"option_grant", company_account.company_id.to_le_bytes().as_ref(), option_grant.employee.as_ref(), option_grant.grant_id.to_le_bytes().as_ref()], bump = option_grant.bump)]
+    pub option_grant: Account<'info, OptionGrant>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
 }
 
 #[derive(Accounts)]
-pub struct InitializeGlobalVault<'info> {
+pub struct ExerciseOptions<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(init, payer = admin, space = 8 + 32 + 1, seeds = [b"vault_authority"], bump)]
-    pub global_vault: Account<'info, GlobalProgramVault>,
-    pub usdc_token_account: Account<'info, TokenAccount>,
+    pub employee: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, has_one = employee, seeds = [b"option_grant", company_account.company_id.to_le_bytes().as_ref(), employee.key().as_ref(), option_grant.grant_id.to_le_bytes().as_ref()], bump = option_grant.bump)]
+    pub option_grant: Account<'info, OptionGrant>,
+    #[account(mut, seeds = [b"vault", employee.key().as_ref(), mint_seed(&employee_vault.mint)], bump = employee_vault.bump)]
+    pub employee_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = employee, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), employee.key().as_ref()], bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), employee.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(company_id: u64)]
-pub struct ActivateCompany<'info> {
+pub struct ReclaimOptionGrant<'info> {
+    #[account(mut)]
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"option_pool", company_account.company_id.to_le_bytes().as_ref()], bump = option_pool.bump)]
+    pub option_pool: Account<'info, OptionPool>,
+    #[account(mut, close = company_admin, seeds = [b"option_grant", company_account.company_id.to_le_bytes().as_ref(), option_grant.employee.as_ref(), option_grant.grant_id.to_le_bytes().as_ref()], bump = option_grant.bump)]
+    pub option_grant: Account<'info, OptionGrant>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(class_id: u8)]
+pub struct CreateShareClass<'info> {
     #[account(mut)]
-    pub platform_admin: Signer<'info>, // Must be the platform admin
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
     #[account(
         init,
-        payer = platform_admin,
-        space = 8 + 8 + 32 + 16 + 8 + 8 + 1 + 1, // 82 bytes
-        seeds = [b"company", company_id.to_le_bytes().as_ref()],
+        payer = company_admin,
+        space = 8 + ShareClass::INIT_SPACE,
+        seeds = [b"share_class", company_account.company_id.to_le_bytes().as_ref(), &[class_id]],
         bump
     )]
+    pub share_class: Account<'info, ShareClass>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateShareClass<'info> {
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"share_class", company_account.company_id.to_le_bytes().as_ref(), &[share_class.class_id]], bump = share_class.bump)]
+    pub share_class: Account<'info, ShareClass>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConvertShareClass<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
     pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"share_class", company_account.company_id.to_le_bytes().as_ref(), &[share_class.class_id]], bump = share_class.bump)]
+    pub share_class: Account<'info, ShareClass>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), class_seed(&share_class.class_id), holder.key().as_ref()], bump = preferred_position.bump)]
+    pub preferred_position: Account<'info, PositionAccount>,
+    #[account(init_if_needed, payer = holder, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), holder.key().as_ref()], bump)]
+    pub common_position: Account<'info, PositionAccount>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Deposit<'info> {
+#[instruction(request_id: u64)]
+pub struct RequestWithdrawal<'info> {
     #[account(mut)]
     pub investor: Signer<'info>,
-    #[account(init_if_needed, payer = investor, space = 8 + 32 + 16 + 1, seeds = [b"vault", investor.key().as_ref()], bump)]
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"vault", investor.key().as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
     pub investor_vault: Account<'info, InvestorVault>,
+    #[account(seeds = [b"quote_mint", mint.key().as_ref()], bump = quote_mint.bump, has_one = mint)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub investor_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = investor,
+        space = 8 + PendingWithdrawal::INIT_SPACE,
+        seeds = [b"withdrawal", investor.key().as_ref(), request_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(request_id: u64)]
+pub struct RequestCompanyWithdrawal<'info> {
     #[account(mut)]
-    pub investor_token_account: Account<'info, TokenAccount>,
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(seeds = [b"quote_mint", mint.key().as_ref()], bump = quote_mint.bump, has_one = mint)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = company_admin,
+        space = 8 + PendingWithdrawal::INIT_SPACE,
+        seeds = [b"withdrawal", company_admin.key().as_ref(), request_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    /// Approved proposal, required once the company has a multisig
     #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub proposal: Option<Account<'info, CompanyProposal>>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+pub struct ReleaseWithdrawal<'info> {
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, close = requester, has_one = requester, has_one = mint, has_one = destination, seeds = [b"withdrawal", requester.key().as_ref(), pending_withdrawal.request_id.to_le_bytes().as_ref()], bump = pending_withdrawal.bump)]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    /// CHECK: Requester, checked against the withdrawal via has_one; receives the rent
     #[account(mut)]
-    pub investor: Signer<'info>,
-    #[account(mut, seeds = [b"vault", investor.key().as_ref()], bump)]
-    pub investor_vault: Account<'info, InvestorVault>,
-    #[account(seeds = [b"vault_authority"], bump)]
+    pub requester: UncheckedAccount<'info>,
+    #[account(seeds = [b"vault_authority"], bump = global_vault.bump)]
     pub global_vault: Account<'info, GlobalProgramVault>,
+    #[account(seeds = [b"quote_mint", mint.key().as_ref()], bump = quote_mint.bump, has_one = mint, has_one = vault_token_account)]
+    pub quote_mint: Account<'info, QuoteMint>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub investor_token_account: Account<'info, TokenAccount>,
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct BuyShares<'info> {
+pub struct FreezeWithdrawal<'info> {
+    #[account(address = platform_config.compliance @ DonatradeError::Unauthorized)]
+    pub compliance: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"withdrawal", pending_withdrawal.requester.as_ref(), pending_withdrawal.request_id.to_le_bytes().as_ref()], bump = pending_withdrawal.bump)]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(mut, address = platform_config.compliance @ DonatradeError::Unauthorized)]
+    pub compliance: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, close = requester, has_one = requester, seeds = [b"withdrawal", requester.key().as_ref(), pending_withdrawal.request_id.to_le_bytes().as_ref()], bump = pending_withdrawal.bump)]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    /// CHECK: Requester, checked against the withdrawal via has_one; receives the rent
     #[account(mut)]
-    pub investor: Signer<'info>,
-    #[account(mut, seeds = [b"vault", investor.key().as_ref()], bump)]
-    pub investor_vault: Account<'info, InvestorVault>,
+    pub requester: UncheckedAccount<'info>,
+    /// Required when cancelling an investor withdrawal
+    #[account(mut, seeds = [b"vault", investor_vault.owner.as_ref(), mint_seed(&investor_vault.mint)], bump = investor_vault.bump)]
+    pub investor_vault: Option<Account<'info, InvestorVault>>,
+    /// Required when cancelling a company withdrawal
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Option<Account<'info, CompanyAccount>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Donate<'info> {
     #[account(mut)]
+    pub donor: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
     pub company_account: Account<'info, CompanyAccount>,
-    #[account(init_if_needed, payer = investor, space = 8 + 32 + 8 + 16 + 1, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump)]
-    pub position: Account<'info, PositionAccount>,
+    #[account(mut, seeds = [b"vault", donor.key().as_ref(), mint_seed(&donor_vault.mint)], bump = donor_vault.bump)]
+    pub donor_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = donor, space = 8 + DonorRecord::INIT_SPACE, seeds = [b"donor", company_account.company_id.to_le_bytes().as_ref(), donor.key().as_ref()], bump)]
+    pub donor_record: Account<'info, DonorRecord>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SellShares<'info> {
+pub struct PublishDonorTotal<'info> {
+    pub donor: Signer<'info>,
+    #[account(mut, has_one = donor, seeds = [b"donor", donor_record.company_id.to_le_bytes().as_ref(), donor.key().as_ref()], bump = donor_record.bump)]
+    pub donor_record: Account<'info, DonorRecord>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct HideDonorTotal<'info> {
+    pub donor: Signer<'info>,
+    #[account(mut, has_one = donor, seeds = [b"donor", donor_record.company_id.to_le_bytes().as_ref(), donor.key().as_ref()], bump = donor_record.bump)]
+    pub donor_record: Account<'info, DonorRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(tender_id: u64)]
+pub struct CreateTenderOffer<'info> {
     #[account(mut)]
-    pub investor: Signer<'info>,
-    #[account(mut, seeds = [b"vault", investor.key().as_ref()], bump)]
-    pub investor_vault: Account<'info, InvestorVault>,
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(
+        init,
+        payer = company_admin,
+        space = 8 + TenderOffer::INIT_SPACE,
+        seeds = [b"tender_offer", company_account.company_id.to_le_bytes().as_ref(), tender_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tender_offer: Account<'info, TenderOffer>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TenderShares<'info> {
     #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
     pub company_account: Account<'info, CompanyAccount>,
-    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), investor.key().as_ref()], bump)]
+    #[account(mut, seeds = [b"tender_offer", company_account.company_id.to_le_bytes().as_ref(), tender_offer.tender_id.to_le_bytes().as_ref()], bump = tender_offer.bump)]
+    pub tender_offer: Account<'info, TenderOffer>,
+    #[account(init_if_needed, payer = holder, space = 8 + Tender::INIT_SPACE, seeds = [b"tender", tender_offer.key().as_ref(), holder.key().as_ref()], bump)]
+    pub tender: Account<'info, Tender>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), holder.key().as_ref()], bump = position.bump)]
     pub position: Account<'info, PositionAccount>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
@@ -767,83 +8114,158 @@ pub struct SellShares<'info> {
 }
 
 #[derive(Accounts)]
-pub struct AuthorizeDecryption<'info> {
+pub struct RevealTenderTotal<'info> {
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"tender_offer", company_account.company_id.to_le_bytes().as_ref(), tender_offer.tender_id.to_le_bytes().as_ref()], bump = tender_offer.bump)]
+    pub tender_offer: Account<'info, TenderOffer>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signatures
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleTender<'info> {
     #[account(mut)]
-    pub investor: Signer<'info>,
-    /// CHECK: The address to grant access to
-    pub allowed_address: UncheckedAccount<'info>,
-    /// CHECK: Validated by Inco program
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"tender_offer", company_account.company_id.to_le_bytes().as_ref(), tender_offer.tender_id.to_le_bytes().as_ref()], bump = tender_offer.bump)]
+    pub tender_offer: Account<'info, TenderOffer>,
+    /// Settled tenders are closed and their rent returned to the holder
+    #[account(mut, close = holder, has_one = holder, seeds = [b"tender", tender_offer.key().as_ref(), holder.key().as_ref()], bump = tender.bump)]
+    pub tender: Account<'info, Tender>,
+    /// CHECK: Tender owner, checked against the tender via has_one
     #[account(mut)]
-    pub allowance_account: UncheckedAccount<'info>,
+    pub holder: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"vault", holder.key().as_ref(), mint_seed(&holder_vault.mint)], bump = holder_vault.bump)]
+    pub holder_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), holder.key().as_ref()], bump = holder_position.bump)]
+    pub holder_position: Account<'info, PositionAccount>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateOffering<'info> {
+pub struct FinalizeTenderOffer<'info> {
     #[account(mut)]
-    pub company_admin: Signer<'info>,
-    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
     pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, seeds = [b"tender_offer", company_account.company_id.to_le_bytes().as_ref(), tender_offer.tender_id.to_le_bytes().as_ref()], bump = tender_offer.bump)]
+    pub tender_offer: Account<'info, TenderOffer>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawCompanyFunds<'info> {
+pub struct SnapshotRights<'info> {
     #[account(mut)]
-    pub company_admin: Signer<'info>,
-    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub holder: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
     pub company_account: Account<'info, CompanyAccount>,
-    #[account(seeds = [b"vault_authority"], bump)]
-    pub global_vault: Account<'info, GlobalProgramVault>,
+    #[account(seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), holder.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(
+        init,
+        payer = holder,
+        space = 8 + RightsEntitlement::INIT_SPACE,
+        seeds = [b"rights", company_account.company_id.to_le_bytes().as_ref(), company_account.rights_period.round.to_le_bytes().as_ref(), holder.key().as_ref()],
+        bump
+    )]
+    pub entitlement: Account<'info, RightsEntitlement>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExerciseRights<'info> {
     #[account(mut)]
-    pub admin_token_account: Account<'info, TokenAccount>,
+    pub holder: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    #[account(mut, has_one = holder, seeds = [b"rights", company_account.company_id.to_le_bytes().as_ref(), entitlement.round.to_le_bytes().as_ref(), holder.key().as_ref()], bump = entitlement.bump)]
+    pub entitlement: Account<'info, RightsEntitlement>,
+    #[account(mut, seeds = [b"vault", holder.key().as_ref(), mint_seed(&holder_vault.mint)], bump = holder_vault.bump)]
+    pub holder_vault: Account<'info, InvestorVault>,
+    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), holder.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(seeds = [b"agreement", company_account.company_id.to_le_bytes().as_ref(), holder.key().as_ref()], bump = agreement_acceptance.bump)]
+    pub agreement_acceptance: Account<'info, AgreementAcceptance>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestExitShareCounts<'info> {
     #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub company_admin: Signer<'info>,
+    #[account(has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(offer_id: u64)]
-pub struct CreateOffer<'info> {
+pub struct DeclareExit<'info> {
     #[account(mut)]
-    pub seller: Signer<'info>,
+    pub company_admin: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(address = platform_config.admin @ DonatradeError::Unauthorized)]
+    pub platform_admin: Signer<'info>,
+    #[account(mut, has_one = company_admin, seeds = [b"company", company_account.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
     pub company_account: Account<'info, CompanyAccount>,
-    #[account(mut, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), seller.key().as_ref()], bump = seller_position.bump)]
-    pub seller_position: Account<'info, PositionAccount>,
     #[account(
         init,
-        payer = seller,
-        space = 8 + 8 + 32 + 8 + 16 + 8 + 1 + 1, // Added 16 for escrowed_shares (Euint128)
-        seeds = [b"offer", seller.key().as_ref(), offer_id.to_le_bytes().as_ref()],
+        payer = company_admin,
+        space = 8 + ExitEvent::INIT_SPACE,
+        seeds = [b"exit", company_account.company_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub offer_account: Account<'info, OfferAccount>,
+    pub exit_event: Account<'info, ExitEvent>,
+    /// Approved proposal, required once the company has a multisig
+    #[account(mut)]
+    pub proposal: Option<Account<'info, CompanyProposal>>,
+    /// CHECK: Instructions sysvar, read by Inco to find the attestation signature
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    pub instructions: UncheckedAccount<'info>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ExecuteTrade<'info> {
+pub struct ClaimExitProceeds<'info> {
     #[account(mut)]
-    pub buyer: Signer<'info>,
-    #[account(mut)]
-    pub offer_account: Account<'info, OfferAccount>,
-    #[account(mut)]
-    pub buyer_vault: Account<'info, InvestorVault>,
-    #[account(mut)]
-    pub seller_vault: Account<'info, InvestorVault>,
-    /// The company for which shares are being traded
-    pub company_account: Account<'info, CompanyAccount>,
-    /// Buyer's position account for receiving shares
-    #[account(init_if_needed, payer = buyer, space = 8 + 32 + 8 + 16 + 1, seeds = [b"position", company_account.company_id.to_le_bytes().as_ref(), buyer.key().as_ref()], bump)]
-    pub buyer_position: Account<'info, PositionAccount>,
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, seeds = [b"exit", exit_event.company_id.to_le_bytes().as_ref()], bump = exit_event.bump)]
+    pub exit_event: Account<'info, ExitEvent>,
+    /// Extinguished by the claim
+    #[account(mut, close = owner, has_one = owner, seeds = [b"position", exit_event.company_id.to_le_bytes().as_ref(), class_seed(&position.class_id), owner.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, PositionAccount>,
+    #[account(mut, seeds = [b"vault", owner.key().as_ref(), mint_seed(&owner_vault.mint)], bump = owner_vault.bump)]
+    pub owner_vault: Account<'info, InvestorVault>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
@@ -853,17 +8275,41 @@ pub struct ExecuteTrade<'info> {
 pub struct TransferShares<'info> {
     #[account(mut)]
     pub sender: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
     /// CHECK: Recipient of the shares
     pub receiver: UncheckedAccount<'info>,
-    #[account(mut, seeds = [b"position", sender_position.company_id.to_le_bytes().as_ref(), sender.key().as_ref()], bump = sender_position.bump)]
+    #[account(mut, seeds = [b"position", sender_position.company_id.to_le_bytes().as_ref(), class_seed(&sender_position.class_id), sender.key().as_ref()], bump = sender_position.bump)]
     pub sender_position: Account<'info, PositionAccount>,
-    #[account(init_if_needed, payer = sender, space = 8 + 32 + 8 + 16 + 1, seeds = [b"position", sender_position.company_id.to_le_bytes().as_ref(), receiver.key().as_ref()], bump)]
+    #[account(seeds = [b"company", sender_position.company_id.to_le_bytes().as_ref()], bump = company_account.bump)]
+    pub company_account: Account<'info, CompanyAccount>,
+    /// Required unless transferring common stock
+    #[account(seeds = [b"share_class", sender_position.company_id.to_le_bytes().as_ref(), &[sender_position.class_id]], bump = share_class.bump)]
+    pub share_class: Option<Account<'info, ShareClass>>,
+    #[account(init_if_needed, payer = sender, space = 8 + PositionAccount::INIT_SPACE, seeds = [b"position", sender_position.company_id.to_le_bytes().as_ref(), class_seed(&sender_position.class_id), receiver.key().as_ref()], bump)]
     pub receiver_position: Account<'info, PositionAccount>,
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct TransferCusd<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    /// CHECK: Recipient of the cUSD
+    pub receiver: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"vault", sender.key().as_ref(), mint_seed(&sender_vault.mint)], bump = sender_vault.bump)]
+    pub sender_vault: Account<'info, InvestorVault>,
+    #[account(init_if_needed, payer = sender, space = 8 + InvestorVault::INIT_SPACE, seeds = [b"vault", receiver.key().as_ref(), mint_seed(&sender_vault.mint)], bump)]
+    pub receiver_vault: Account<'info, InvestorVault>,
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[error_code]
 pub enum DonatradeError {
     #[msg("Account is not initialized")]
@@ -876,4 +8322,595 @@ pub enum DonatradeError {
     InsufficientShares,
     #[msg("Insufficient funds")]
     InsufficientFunds,
+    #[msg("Investor has not accepted the current legal agreement")]
+    AgreementNotAccepted,
+    #[msg("Legal agreement hash or version does not match the company's current agreement")]
+    AgreementMismatch,
+    #[msg("Signer is not authorized for this action")]
+    Unauthorized,
+    #[msg("Invalid company signer set or threshold")]
+    InvalidSignerSet,
+    #[msg("Signer is not a company multisig signer")]
+    NotCompanySigner,
+    #[msg("Company multisig is not enabled")]
+    MultisigNotEnabled,
+    #[msg("Company multisig is enabled; use a proposal")]
+    MultisigEnabled,
+    #[msg("An approved proposal is required for this action")]
+    ProposalRequired,
+    #[msg("Proposal does not match this action")]
+    ProposalMismatch,
+    #[msg("Proposal was created for a previous signer set")]
+    StaleProposal,
+    #[msg("Proposal has already been executed")]
+    ProposalExecuted,
+    #[msg("Proposal does not have enough approvals")]
+    InsufficientApprovals,
+    #[msg("Signer has already approved this proposal")]
+    AlreadyApproved,
+    #[msg("This instruction is paused")]
+    ProgramPaused,
+    #[msg("Company is paused")]
+    CompanyPaused,
+    #[msg("Account is already on the current layout version")]
+    AlreadyMigrated,
+    #[msg("Account layout version is not supported")]
+    UnsupportedAccountVersion,
+    #[msg("Balance changed since the close request; request again")]
+    StaleCloseRequest,
+    #[msg("Missing allowance accounts for decryption grants")]
+    MissingAllowanceAccounts,
+    #[msg("Allowed address does not match the expected grantee")]
+    AllowedAddressMismatch,
+    #[msg("Private pricing is not enabled")]
+    PrivatePricingDisabled,
+    #[msg("This company uses private pricing; provide an encrypted price")]
+    PrivatePriceRequired,
+    #[msg("Not available in the company's current offering mode")]
+    WrongOfferingMode,
+    #[msg("Invalid auction parameters")]
+    InvalidAuctionParams,
+    #[msg("Auction is not open for bids")]
+    AuctionNotOpen,
+    #[msg("Auction cannot be closed or priced yet")]
+    AuctionNotClosable,
+    #[msg("Auction clearing price has not been finalized")]
+    AuctionNotPriced,
+    #[msg("Bids must be settled in submission order")]
+    BidOutOfOrder,
+    #[msg("Shares changed since the exhaustion check; request again")]
+    StaleExhaustionCheck,
+    #[msg("Order book is full")]
+    OrderBookFull,
+    #[msg("Order not found in the book")]
+    OrderNotFound,
+    #[msg("No crossing orders to match")]
+    NoCrossingOrders,
+    #[msg("Invalid order parameters")]
+    InvalidOrder,
+    #[msg("Order book does not belong to this company or these traders")]
+    OrderBookMismatch,
+    #[msg("Invalid counterparty or allowlist")]
+    InvalidAllowlist,
+    #[msg("This offer is directed to other buyers")]
+    NotOfferCounterparty,
+    #[msg("A swap must exchange shares of two different companies")]
+    InvalidSwap,
+    #[msg("Invalid SAFE terms")]
+    InvalidSafeTerms,
+    #[msg("SAFE round has already been priced")]
+    SafeRoundPriced,
+    #[msg("SAFE round has not been priced yet")]
+    SafeRoundNotPriced,
+    #[msg("Invalid option grant terms")]
+    InvalidOptionTerms,
+    #[msg("Option grant has already been terminated")]
+    OptionGrantTerminated,
+    #[msg("Options cannot be exercised at this time")]
+    ExerciseWindowClosed,
+    #[msg("Options can still be exercised")]
+    ExerciseWindowOpen,
+    #[msg("Invalid share class")]
+    InvalidShareClass,
+    #[msg("A share class account is required for non-common shares")]
+    ShareClassRequired,
+    #[msg("Shares of this class are not transferable")]
+    ClassNotTransferable,
+    #[msg("Company has exited; trading is closed")]
+    CompanyExited,
+    #[msg("Invalid exit declaration")]
+    InvalidExit,
+    #[msg("Invalid tender offer parameters")]
+    InvalidTenderOffer,
+    #[msg("Tender offer window has closed")]
+    TenderWindowClosed,
+    #[msg("Tender offer window is still open")]
+    TenderWindowOpen,
+    #[msg("All tenders must be settled first, or the settlement grace period must pass")]
+    TendersUnsettled,
+    #[msg("Invalid rights period")]
+    InvalidRightsPeriod,
+    #[msg("New supply is reserved for existing holders until the rights period ends")]
+    RightsPeriodActive,
+    #[msg("No rights period is open")]
+    RightsPeriodClosed,
+    #[msg("Sender and receiver must differ")]
+    SelfTransfer,
+    #[msg("Vault and company are quoted in different mints")]
+    QuoteMintMismatch,
+    #[msg("Quote mint is not accepting deposits")]
+    QuoteMintInactive,
+    #[msg("Invalid quote mint")]
+    InvalidQuoteMint,
+    #[msg("Withdrawals must go through the delayed queue")]
+    WithdrawalDelayRequired,
+    #[msg("Delayed withdrawals are not enabled")]
+    WithdrawalNotDelayed,
+    #[msg("Invalid withdrawal delay")]
+    InvalidWithdrawalDelay,
+    #[msg("Withdrawal is still in its challenge period")]
+    WithdrawalLocked,
+    #[msg("Withdrawal is frozen by compliance")]
+    WithdrawalFrozen,
+    #[msg("The vault or company the withdrawal came from is required")]
+    WithdrawalSourceRequired,
+    #[msg("Withdrawal limit for the current 24h window exceeded")]
+    WithdrawalLimitExceeded,
+    #[msg("Primary purchase limit for the current 24h window exceeded")]
+    PurchaseLimitExceeded,
+    #[msg("Order can still be matched; only unsettleable head orders can be evicted")]
+    OrderSettleable,
+    #[msg("Common stock cannot change hands while a rights period is open")]
+    RightsTransferFrozen,
+    #[msg("The tendered total must be revealed before tenders are settled")]
+    TenderTotalUnrevealed,
+    #[msg("Revealed demand does not clear at this level")]
+    ClearingLevelMismatch,
+    #[msg("Outstanding shares are not tracked for this company or class")]
+    ShareCountUntracked,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::legacy::*;
+    use super::*;
+
+    /// Raw account bytes as a legacy layout left them on chain.
+    fn legacy_bytes<T: Discriminator, V: LegacyLayout<T> + AnchorSerialize>(v: &V) -> Vec<u8> {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        v.serialize(&mut data).unwrap();
+        assert_eq!(data.len(), V::LEN);
+        data
+    }
+
+    /// Serializes a migrated account into a buffer of its new size and reads it back.
+    fn roundtrip<T: AccountSerialize + AccountDeserialize>(account: &T, len: usize) -> T {
+        let mut data = vec![0u8; len];
+        account.try_serialize(&mut &mut data[..]).unwrap();
+        T::try_deserialize(&mut &data[..]).unwrap()
+    }
+
+    #[test]
+    fn migrates_v1_company_account() {
+        let admin = Pubkey::new_unique();
+        let data = legacy_bytes(&CompanyAccountV1 {
+            company_id: 7,
+            company_admin: admin,
+            cusd: Euint128(11),
+            shares_available: Euint128(12),
+            price_per_share: 100,
+            active: true,
+            bump: 254,
+        });
+
+        let company: CompanyAccount = upgrade_account_data(&data, COMPANY_ACCOUNT_LAYOUTS).unwrap();
+        let company = roundtrip(&company, 8 + CompanyAccount::INIT_SPACE);
+        assert_eq!(company.version, CURRENT_ACCOUNT_VERSION);
+        assert_eq!(company.company_id, 7);
+        assert_eq!(company.company_admin, admin);
+        assert_eq!(company.cusd.0, 11);
+        assert_eq!(company.shares_available.0, 12);
+        assert_eq!(company.price_per_share, 100);
+        assert!(company.active && !company.paused && !company.private_pricing);
+        assert_eq!(company.threshold, 0);
+        assert_eq!(company.quote_mint, Pubkey::default());
+        assert_eq!(company.bump, 254);
+    }
+
+    #[test]
+    fn migrates_v2_company_account() {
+        let signer = Pubkey::new_unique();
+        let mut signers = [Pubkey::default(); MAX_COMPANY_SIGNERS];
+        signers[0] = signer;
+        let data = legacy_bytes(&CompanyAccountV2 {
+            version: 2,
+            company_id: 7,
+            company_admin: Pubkey::new_unique(),
+            cusd: Euint128(11),
+            shares_available: Euint128(12),
+            price_per_share: 100,
+            legal_agreement_hash: [9; 32],
+            legal_agreement_version: 3,
+            signers,
+            signer_count: 1,
+            threshold: 1,
+            signer_epoch: 4,
+            paused: true,
+            active: true,
+            bump: 254,
+            reserved: [0; 32],
+        });
+
+        let company: CompanyAccount = upgrade_account_data(&data, COMPANY_ACCOUNT_LAYOUTS).unwrap();
+        let company = roundtrip(&company, 8 + CompanyAccount::INIT_SPACE);
+        assert_eq!(company.version, CURRENT_ACCOUNT_VERSION);
+        assert_eq!(company.legal_agreement_hash, [9; 32]);
+        assert_eq!(company.legal_agreement_version, 3);
+        assert_eq!(company.signers[0], signer);
+        assert_eq!((company.signer_count, company.threshold), (1, 1));
+        assert_eq!(company.signer_epoch, 4);
+        assert!(company.paused && company.active && !company.exited);
+        assert_eq!(company.auditor, Pubkey::default());
+        assert_eq!(company.bump, 254);
+    }
+
+    #[test]
+    fn migrates_v1_and_v2_offers() {
+        let seller = Pubkey::new_unique();
+        let v1 = legacy_bytes(&OfferAccountV1 {
+            offer_id: 1,
+            seller,
+            company_id: 7,
+            share_amount: 50,
+            escrowed_shares: Euint128(13),
+            price_per_share: 100,
+            is_active: true,
+            bump: 253,
+        });
+        let v2 = legacy_bytes(&OfferAccountV2 {
+            version: 2,
+            offer_id: 1,
+            seller,
+            company_id: 7,
+            share_amount: 50,
+            escrowed_shares: Euint128(13),
+            price_per_share: 100,
+            is_active: true,
+            bump: 253,
+            reserved: [0; 32],
+        });
+
+        for data in [v1, v2] {
+            let offer: OfferAccount = upgrade_account_data(&data, OFFER_ACCOUNT_LAYOUTS).unwrap();
+            let offer = roundtrip(&offer, 8 + OfferAccount::INIT_SPACE);
+            assert_eq!(offer.version, CURRENT_ACCOUNT_VERSION);
+            assert_eq!((offer.offer_id, offer.company_id), (1, 7));
+            assert_eq!(offer.seller, seller);
+            assert_eq!(offer.share_amount, 50);
+            assert_eq!(offer.escrowed_shares.0, 13);
+            assert_eq!(offer.price_per_share, 100);
+            assert!(offer.is_active && !offer.private_pricing);
+            assert_eq!(offer.class_id, COMMON_CLASS_ID);
+            assert_eq!(offer.allowlist_count, 0);
+            assert_eq!(offer.bump, 253);
+        }
+    }
+
+    #[test]
+    fn migrates_v1_position_and_vault() {
+        let owner = Pubkey::new_unique();
+        let data = legacy_bytes(&PositionAccountV1 {
+            owner,
+            company_id: 7,
+            encrypted_shares: Euint128(14),
+            bump: 252,
+        });
+        let position: PositionAccount =
+            upgrade_account_data(&data, POSITION_ACCOUNT_LAYOUTS).unwrap();
+        let position = roundtrip(&position, 8 + PositionAccount::INIT_SPACE);
+        assert_eq!(position.version, CURRENT_ACCOUNT_VERSION);
+        assert_eq!(position.owner, owner);
+        assert_eq!(position.company_id, 7);
+        assert_eq!(position.class_id, COMMON_CLASS_ID);
+        assert_eq!(position.encrypted_shares.0, 14);
+        assert_eq!(position.bump, 252);
+
+        let data = legacy_bytes(&InvestorVaultV1 {
+            owner,
+            cusd: Euint128(15),
+            bump: 251,
+        });
+        let vault: InvestorVault = upgrade_account_data(&data, INVESTOR_VAULT_LAYOUTS).unwrap();
+        let vault = roundtrip(&vault, 8 + InvestorVault::INIT_SPACE);
+        assert_eq!(vault.version, CURRENT_ACCOUNT_VERSION);
+        assert_eq!(vault.owner, owner);
+        assert_eq!(vault.cusd.0, 15);
+        assert_eq!(vault.mint, Pubkey::default());
+        assert_eq!(vault.bump, 251);
+    }
+
+    #[test]
+    fn migrates_v2_vault() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let data = legacy_bytes(&InvestorVaultV2 {
+            version: 2,
+            owner,
+            cusd: Euint128(16),
+            bump: 250,
+            mint,
+        });
+        assert!(InvestorVault::try_deserialize(&mut &data[..]).is_err());
+
+        let vault: InvestorVault = upgrade_account_data(&data, INVESTOR_VAULT_LAYOUTS).unwrap();
+        let vault = roundtrip(&vault, 8 + InvestorVault::INIT_SPACE);
+        assert_eq!(vault.version, CURRENT_ACCOUNT_VERSION);
+        assert_eq!(vault.owner, owner);
+        assert_eq!(vault.cusd.0, 16);
+        assert_eq!(vault.bump, 250);
+        assert_eq!(vault.mint, mint);
+        assert_eq!(vault.purchases, VelocityCounter::default());
+        assert_eq!(vault.velocity_override, None);
+    }
+
+    #[test]
+    fn reads_and_migrates_v2_positions() {
+        let owner = Pubkey::new_unique();
+        let data = legacy_bytes(&PositionAccountV2 {
+            version: 2,
+            owner,
+            company_id: 7,
+            encrypted_shares: Euint128(14),
+            bump: 252,
+            reserved: [0; 32],
+        });
+        assert_eq!(data.len(), 8 + PositionAccount::INIT_SPACE);
+
+        // class_id sits in the old reserved space, so a v2 position is readable as is
+        let position = PositionAccount::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(position.version, 2);
+        assert_eq!(position.class_id, COMMON_CLASS_ID);
+        assert_eq!(position.encrypted_shares.0, 14);
+
+        let position: PositionAccount =
+            upgrade_account_data(&data, POSITION_ACCOUNT_LAYOUTS).unwrap();
+        let position = roundtrip(&position, 8 + PositionAccount::INIT_SPACE);
+        assert_eq!(position.version, CURRENT_ACCOUNT_VERSION);
+        assert_eq!(position.owner, owner);
+        assert_eq!(position.company_id, 7);
+        assert_eq!(position.class_id, COMMON_CLASS_ID);
+        assert_eq!(position.bump, 252);
+
+        let mut migrated = vec![0u8; 8 + PositionAccount::INIT_SPACE];
+        position.try_serialize(&mut &mut migrated[..]).unwrap();
+        assert_eq!(
+            upgrade_account_data(&migrated, POSITION_ACCOUNT_LAYOUTS).err(),
+            Some(DonatradeError::AlreadyMigrated.into())
+        );
+    }
+
+    #[test]
+    fn rejects_current_and_unknown_layouts() {
+        let current = CompanyAccount {
+            version: CURRENT_ACCOUNT_VERSION,
+            ..Default::default()
+        };
+        let mut data = vec![0u8; 8 + CompanyAccount::INIT_SPACE];
+        current.try_serialize(&mut &mut data[..]).unwrap();
+        assert_eq!(
+            upgrade_account_data(&data, COMPANY_ACCOUNT_LAYOUTS).err(),
+            Some(DonatradeError::AlreadyMigrated.into())
+        );
+
+        data.truncate(8 + 20);
+        data[8] = 1;
+        assert_eq!(
+            upgrade_account_data(&data, COMPANY_ACCOUNT_LAYOUTS).err(),
+            Some(DonatradeError::UnsupportedAccountVersion.into())
+        );
+
+        let offer = legacy_bytes(&OfferAccountV1 {
+            offer_id: 1,
+            seller: Pubkey::new_unique(),
+            company_id: 7,
+            share_amount: 50,
+            escrowed_shares: Euint128(13),
+            price_per_share: 100,
+            is_active: true,
+            bump: 253,
+        });
+        assert!(upgrade_account_data(&offer, COMPANY_ACCOUNT_LAYOUTS).is_err());
+    }
+
+    /// Serialized length of `value`, for checking hand-written `Space` impls.
+    fn serialized_len<T: AnchorSerialize>(value: &T) -> usize {
+        let mut data = Vec::new();
+        value.serialize(&mut data).unwrap();
+        data.len()
+    }
+
+    #[test]
+    fn handle_accounts_reserve_their_serialized_size() {
+        let limits = Some(VelocityLimits::default());
+        let vault = InvestorVault {
+            velocity_override: limits,
+            ..Default::default()
+        };
+        assert_eq!(serialized_len(&vault), InvestorVault::INIT_SPACE);
+        let company = CompanyAccount {
+            velocity_override: limits,
+            ..Default::default()
+        };
+        assert_eq!(serialized_len(&company), CompanyAccount::INIT_SPACE);
+        assert_eq!(
+            serialized_len(&PositionAccount::default()),
+            PositionAccount::INIT_SPACE
+        );
+        assert_eq!(
+            serialized_len(&OfferAccount::default()),
+            OfferAccount::INIT_SPACE
+        );
+        assert_eq!(
+            serialized_len(&DutchSchedule::default()),
+            DutchSchedule::INIT_SPACE
+        );
+    }
+
+    #[test]
+    fn marginal_split_checks_the_clearing_level() {
+        let auction = Auction {
+            company_id: 7,
+            auction_id: 1,
+            supply: 100,
+            price_levels: [10, 20, 30, 0, 0, 0, 0, 0],
+            level_count: 3,
+            demand: [Euint128::default(); MAX_AUCTION_PRICE_LEVELS],
+            remaining: Euint128::default(),
+            closes_at: 0,
+            clearing_price: 0,
+            bid_count: 0,
+            settled_count: 0,
+            status: AuctionStatus::Closed,
+            bump: 255,
+            clearing_level: 0,
+            marginal_supply: 0,
+            marginal_demand: 0,
+        };
+        // 60 shares bid above level 1 fill in full; 90 at level 1 share the other 40
+        assert_eq!(auction.marginal_split(1, 150, 60).unwrap(), (40, 90));
+        // Demand one level up still meets supply
+        assert!(auction.marginal_split(1, 150, 100).is_err());
+        // Demand falls short at a level above the floor
+        assert!(auction.marginal_split(1, 90, 60).is_err());
+        // Cumulative demand cannot shrink going down the ladder
+        assert!(auction.marginal_split(1, 100, 120).is_err());
+        // The floor clears even when undersubscribed
+        assert_eq!(auction.marginal_split(0, 30, 10).unwrap(), (90, 20));
+        // Nothing is above the top level
+        assert_eq!(auction.marginal_split(2, 120, 0).unwrap(), (100, 120));
+        assert!(auction.marginal_split(2, 120, 5).is_err());
+        assert!(auction.marginal_split(3, 0, 0).is_err());
+    }
+
+    fn tranche(
+        outstanding: u64,
+        preference_per_share: u64,
+        seniority: u8,
+        participating: bool,
+        as_converted: u64,
+    ) -> Tranche {
+        Tranche {
+            outstanding,
+            preference_per_share,
+            seniority,
+            participating,
+            as_converted,
+        }
+    }
+
+    #[test]
+    fn exit_waterfall_pays_preferences_by_seniority_then_the_residual() {
+        let common = tranche(1_000, 0, 0, true, 1);
+
+        // Non-participating preferred takes its preference and never converts, even
+        // when converting would pay more (100_000 / 1_100 > 10)
+        let preferred = tranche(100, 10, 1, false, 1);
+        assert_eq!(exit_waterfall(100_000, &[common, preferred]), vec![99, 10]);
+
+        // Participating preferred also shares the residual as converted; dust stays behind
+        let participating = tranche(200, 10, 1, true, 2);
+        assert_eq!(
+            exit_waterfall(10_000, &[common, participating]),
+            vec![5, 20]
+        );
+
+        // Senior preferences are paid first; a shortfall leaves juniors and common with nothing
+        let senior = tranche(100, 20, 2, false, 1);
+        assert_eq!(
+            exit_waterfall(1_500, &[common, senior, participating]),
+            vec![0, 15, 0]
+        );
+
+        // Within one seniority level a shortfall is shared pro-rata by preference amount
+        let small = tranche(100, 10, 1, false, 1);
+        let large = tranche(50, 40, 1, false, 1);
+        assert_eq!(exit_waterfall(1_500, &[small, large]), vec![5, 20]);
+
+        // Nothing participating: the excess over preferences stays undistributed
+        assert_eq!(exit_waterfall(5_000, &[preferred]), vec![10]);
+    }
+
+    #[test]
+    fn dutch_price_falls_linearly_between_start_and_floor() {
+        let schedule = DutchSchedule {
+            start_price: 100,
+            floor_price: 40,
+            starts_at: 1_000,
+            ends_at: 1_600,
+            ..Default::default()
+        };
+        assert_eq!(schedule.price_at(900), 100);
+        assert_eq!(schedule.price_at(1_000), 100);
+        assert_eq!(schedule.price_at(1_300), 70);
+        assert_eq!(schedule.price_at(1_599), 41);
+        assert_eq!(schedule.price_at(1_600), 40);
+        assert_eq!(schedule.price_at(5_000), 40);
+    }
+
+    #[test]
+    fn safe_converts_at_the_better_of_cap_and_discount() {
+        let round = SafeRound {
+            company_id: 7,
+            round_id: 1,
+            valuation_cap: 1_000_000,
+            discount_bps: 2_000,
+            conversion_price: 0,
+            bump: 255,
+        };
+        // Cap price 5 beats the discounted 8
+        assert_eq!(round.conversion_price_for(10, 200_000), 5);
+        // Discounted 8 beats the cap price 20
+        assert_eq!(round.conversion_price_for(10, 50_000), 8);
+        // Never below one unit
+        assert_eq!(round.conversion_price_for(10, 2_000_000), 1);
+
+        let uncapped = SafeRound {
+            valuation_cap: 0,
+            discount_bps: 0,
+            ..round
+        };
+        assert_eq!(uncapped.conversion_price_for(10, 1), 10);
+    }
+
+    #[test]
+    fn velocity_counter_resets_after_the_window() {
+        let exceeded = DonatradeError::WithdrawalLimitExceeded;
+        let start = VELOCITY_WINDOW * 10;
+        let mut counter = VelocityCounter::default();
+
+        counter.record(start, 60, 100, exceeded).unwrap();
+        counter.record(start + 10, 40, 100, exceeded).unwrap();
+        assert_eq!(counter.used, 100);
+        // Over the limit inside the window, and nothing is counted
+        assert!(counter.record(start + 20, 1, 100, exceeded).is_err());
+        assert!(counter
+            .record(start + VELOCITY_WINDOW - 1, 1, 100, exceeded)
+            .is_err());
+        assert_eq!(counter.used, 100);
+
+        // A new window starts from zero
+        counter
+            .record(start + VELOCITY_WINDOW, 100, 100, exceeded)
+            .unwrap();
+        assert_eq!(
+            (counter.window_start, counter.used),
+            (start + VELOCITY_WINDOW, 100)
+        );
+
+        // A zero limit is unlimited
+        counter
+            .record(start + VELOCITY_WINDOW, 1_000_000, 0, exceeded)
+            .unwrap();
+        assert_eq!(counter.used, 1_000_100);
+    }
 }
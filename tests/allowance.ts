@@ -0,0 +1,47 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { ed25519 } from "@noble/curves/ed25519";
+import { decrypt } from "@inco/solana-sdk/attested-decrypt";
+import assert from "node:assert";
+import { INCO_LIGHTNING_ID, getAllowancePDA, getInvestorVaultPDA } from "../lib/solana";
+import { bytesToHandle } from "../lib/encryption";
+
+/**
+ * Runs against devnet, where the Inco Lightning program and its decryption service live.
+ * Needs the provider wallet to own a USDC vault (deposit once from the app).
+ */
+describe("vault balance sharing", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    const program = anchor.workspace.DonatradeProgram as anchor.Program<any>;
+
+    it("grants and then revokes decryption for another address", async function () {
+        const owner = provider.wallet.publicKey;
+        const [investorVault] = getInvestorVaultPDA(owner);
+        const vault = await (program.account as any).investorVault.fetchNullable(investorVault);
+        if (!vault) this.skip();
+
+        const handle = BigInt(bytesToHandle(vault.cusd));
+        const viewer = Keypair.generate();
+        const [allowanceAccount] = getAllowancePDA(handle, viewer.publicKey);
+        const accounts = {
+            owner,
+            investorVault,
+            allowedAddress: viewer.publicKey,
+            allowanceAccount,
+            incoLightningProgram: INCO_LIGHTNING_ID,
+            systemProgram: SystemProgram.programId,
+        };
+        const reveal = () => decrypt([handle.toString()], {
+            address: viewer.publicKey,
+            signMessage: async (message: Uint8Array) => ed25519.sign(message, viewer.secretKey.slice(0, 32)),
+        });
+
+        await program.methods.shareVaultBalance().accountsPartial(accounts).rpc();
+        const shared = await reveal();
+        assert.strictEqual(shared.plaintexts.length, 1);
+
+        await program.methods.revokeVaultBalance().accountsPartial(accounts).rpc();
+        await assert.rejects(reveal());
+    });
+});